@@ -4,7 +4,7 @@ use std::process::Command;
 fn main() {
     // Get the git commit hash (short)
     let output = Command::new("git")
-        .args(&["rev-parse", "--short", "HEAD"])
+        .args(["rev-parse", "--short", "HEAD"])
         .output()
         .expect("Failed to execute git command");
     let git_hash = String::from_utf8(output.stdout).unwrap();
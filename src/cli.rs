@@ -0,0 +1,169 @@
+use crate::commands;
+use crate::config::{Config, Override};
+use clap::{Parser, Subcommand};
+use serenity::all::{Command as ApplicationCommand, GuildId, Http};
+use std::env;
+use std::path::Path;
+use std::process::exit;
+
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Path of the config file, created with the defaults when it doesn't exist
+    #[arg(
+        short,
+        long,
+        global = true,
+        env = "DESTINY_CONFIG",
+        default_value = "./config.toml"
+    )]
+    pub config: String,
+    /// Overrides a config key, e.g. `--set features.music_player.enabled=true`
+    #[arg(
+        short = 's',
+        long = "set",
+        global = true,
+        value_name = "KEY=VALUE",
+        value_parser = Override::parse_arg
+    )]
+    pub overrides: Vec<Override>,
+    /// Registers the slash commands in this guild instead of globally, can be repeated
+    #[arg(long = "dev-guild", global = true, value_name = "GUILD_ID")]
+    pub dev_guilds: Vec<u64>,
+    /// Prints the effective config with secrets redacted, then exits
+    #[arg(long)]
+    pub print_config: bool,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum CliCommand {
+    /// Starts the bot, the default when no command is given
+    Run,
+    /// Writes the default config file without starting the bot
+    InitConfig {
+        /// Replace the config file if it already exists
+        #[arg(long)]
+        force: bool,
+    },
+    /// Checks the config file and the overrides, exiting with an error if they're invalid
+    CheckConfig,
+    /// Registers the slash commands in one guild, or as the config says
+    RegisterCommands {
+        /// Register the commands in this guild only
+        #[arg(long)]
+        guild: Option<u64>,
+    },
+    /// Removes the registered slash commands, globally or from one guild
+    UnregisterCommands {
+        /// Remove the commands from this guild only
+        #[arg(long)]
+        guild: Option<u64>,
+    },
+    /// Prints the version and build information
+    Version,
+}
+
+impl Cli {
+    /// Overrides from the environment followed by the command line ones, which win.
+    pub fn overrides(&self) -> Vec<Override> {
+        let mut overrides = Override::from_env();
+        overrides.extend(self.overrides.iter().cloned());
+        if !self.dev_guilds.is_empty() {
+            let guilds: Vec<String> = self.dev_guilds.iter().map(u64::to_string).collect();
+            overrides.push(Override {
+                origin: "--dev-guild".to_string(),
+                key: "general.dev_guilds".to_string(),
+                value: format!("[{}]", guilds.join(", ")),
+            });
+        }
+        overrides
+    }
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{}", message);
+    exit(1);
+}
+
+/// Client for the Discord API, with the application ID the command endpoints need.
+async fn discord_http() -> Http {
+    let http = match env::var("DISCORD_TOKEN") {
+        Ok(token) => Http::new(&token),
+        Err(_) => fail("Discord token not found.".to_string()),
+    };
+    match http.get_current_application_info().await {
+        Ok(application) => http.set_application_id(application.id),
+        Err(why) => fail(format!("Failed to get the application: {}", why)),
+    }
+    http
+}
+
+pub fn init_config(cli: &Cli, force: bool) {
+    if Path::new(&cli.config).exists() && !force {
+        fail(format!(
+            "{} already exists, use --force to replace it.",
+            cli.config
+        ));
+    }
+    Config::default().save(&cli.config);
+    println!("Wrote the default config to {}.", cli.config);
+}
+
+pub fn check_config(cli: &Cli) {
+    match Config::check(&cli.config, &cli.overrides()) {
+        Ok((_, unknown_keys)) if unknown_keys.is_empty() => println!("{} is valid.", cli.config),
+        Ok((_, unknown_keys)) => fail(format!(
+            "Invalid config: {} has unknown keys:\n- `{}`",
+            cli.config,
+            unknown_keys.join("`\n- `")
+        )),
+        Err(why) => fail(format!("Invalid config: {}", why)),
+    }
+}
+
+pub async fn register_commands(cli: &Cli, guild: Option<u64>) {
+    let config = match Config::check(&cli.config, &cli.overrides()) {
+        Ok((config, _)) => config,
+        Err(why) => fail(format!("Invalid config: {}", why)),
+    };
+    let http = discord_http().await;
+    let commands = commands::all(&config);
+    let result = match guild {
+        Some(guild) => {
+            poise::builtins::register_in_guild(&http, &commands, GuildId::new(guild)).await
+        }
+        None => commands::register(&http, &commands, &config).await,
+    };
+    match result {
+        Ok(()) => println!("Registered {} commands.", commands.len()),
+        Err(why) => fail(format!("Failed to register the commands: {}", why)),
+    }
+}
+
+pub async fn unregister_commands(guild: Option<u64>) {
+    let http = discord_http().await;
+    let result = match guild {
+        Some(guild) => GuildId::new(guild)
+            .set_commands(&http, vec![])
+            .await
+            .map(|_| ()),
+        None => ApplicationCommand::set_global_commands(&http, vec![])
+            .await
+            .map(|_| ()),
+    };
+    match result {
+        Ok(()) => println!("Removed the commands."),
+        Err(why) => fail(format!("Failed to remove the commands: {}", why)),
+    }
+}
+
+pub fn version() {
+    println!(
+        "Destiny v{} ({}, {})",
+        env!("CARGO_PKG_VERSION"),
+        env!("GIT_HASH"),
+        env!("BUILD_PROFILE")
+    );
+}
@@ -0,0 +1,141 @@
+use crate::CONFIG;
+use crate::commands::{Context, Error, is_privileged, register};
+use crate::utils::message::{error_reply, info_reply, send_reply};
+use crate::{logging, reload};
+use tracing::info;
+
+/// Reloads the config file
+#[poise::command(slash_command, prefix_command, ephemeral, check = "is_privileged")]
+pub async fn reload(ctx: Context<'_>) -> Result<(), Error> {
+    let reply = match reload::reload() {
+        Ok(changes) => {
+            info_reply(
+                Some(ctx.serenity_context()),
+                changes.summary(),
+                Some("Config".to_string()),
+            )
+            .await
+        }
+        Err(why) => {
+            error_reply(
+                Some(ctx.serenity_context()),
+                format!("The config wasn't reloaded:\n```\n{}\n```", why),
+                Some("Config".to_string()),
+            )
+            .await
+        }
+    };
+    send_reply(&ctx, reply).await;
+    Ok(())
+}
+
+/// Registers the slash commands again, in the development guilds if there are any
+#[poise::command(
+    slash_command,
+    prefix_command,
+    ephemeral,
+    rename = "sync-commands",
+    check = "is_privileged"
+)]
+pub async fn sync_commands(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let config = CONFIG.get().unwrap();
+    let commands = &ctx.framework().options().commands;
+    let reply = match register(&ctx.serenity_context().http, commands, &config).await {
+        Ok(()) => {
+            let target = if config.general.dev_guilds.is_empty() {
+                "globally".to_string()
+            } else {
+                format!("in {} development guilds", config.general.dev_guilds.len())
+            };
+            info_reply(
+                Some(ctx.serenity_context()),
+                format!("Registered {} commands {}.", commands.len(), target),
+                Some("Commands".to_string()),
+            )
+            .await
+        }
+        Err(why) => {
+            error_reply(
+                Some(ctx.serenity_context()),
+                format!("Failed to register the commands: {}", why),
+                Some("Commands".to_string()),
+            )
+            .await
+        }
+    };
+    send_reply(&ctx, reply).await;
+    Ok(())
+}
+
+/// Shows or changes which logs are recorded
+#[poise::command(
+    slash_command,
+    prefix_command,
+    ephemeral,
+    rename = "log-filter",
+    subcommands("show", "set", "reset"),
+    subcommand_required,
+    check = "is_privileged"
+)]
+pub async fn log_filter(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+async fn filter_reply(ctx: &Context<'_>, result: Result<(), String>) {
+    let reply = match result {
+        Ok(()) => {
+            info_reply(
+                Some(ctx.serenity_context()),
+                format!("Active filter: `{}`", logging::current_filter()),
+                Some("Logs".to_string()),
+            )
+            .await
+        }
+        Err(why) => {
+            error_reply(
+                Some(ctx.serenity_context()),
+                format!("The filter wasn't changed: {}", why),
+                Some("Logs".to_string()),
+            )
+            .await
+        }
+    };
+    send_reply(ctx, reply).await;
+}
+
+/// Shows the active log filter
+#[poise::command(slash_command, prefix_command, ephemeral, check = "is_privileged")]
+pub async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    filter_reply(&ctx, Ok(())).await;
+    Ok(())
+}
+
+/// Replaces the log filter until the config is reloaded or the bot restarts
+#[poise::command(slash_command, prefix_command, ephemeral, check = "is_privileged")]
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "Filter in RUST_LOG syntax, e.g. info,songbird=debug"]
+    #[rest]
+    directives: String,
+) -> Result<(), Error> {
+    let result = logging::set_filter(&directives);
+    if result.is_ok() {
+        info!(
+            "{} ({}) changed the log filter to {}",
+            ctx.author().name,
+            ctx.author().id,
+            directives
+        );
+    }
+    filter_reply(&ctx, result).await;
+    Ok(())
+}
+
+/// Goes back to the log filter of the config
+#[poise::command(slash_command, prefix_command, ephemeral, check = "is_privileged")]
+pub async fn reset(ctx: Context<'_>) -> Result<(), Error> {
+    let result = logging::reset_filter(&CONFIG.get().unwrap().log);
+    filter_reply(&ctx, result).await;
+    Ok(())
+}
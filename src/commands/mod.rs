@@ -1,3 +1,9 @@
+use crate::CONFIG;
+use crate::config::Config;
+use crate::utils::message::{error_reply, send_reply};
+use serenity::all::{Command, GuildId, Http};
+
+pub mod admin;
 pub mod age;
 pub mod music;
 pub mod ping;
@@ -5,3 +11,62 @@ pub mod ping;
 pub struct Data {} // User data, which is stored and accessible in all command invocations
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
 pub type Context<'a> = poise::Context<'a, Data, Error>;
+
+/// Every command, the music ones only when the music player is enabled.
+pub fn all(config: &Config) -> Vec<poise::Command<Data, Error>> {
+    let mut commands = vec![
+        crate::about(),
+        admin::reload(),
+        admin::sync_commands(),
+        admin::log_filter(),
+        age::age(),
+        ping::ping(),
+    ];
+    if config.features.music_player.enabled {
+        commands.append(&mut music::exports());
+    }
+    commands
+}
+
+/// Registers the slash commands in the development guilds if there are any, globally otherwise.
+pub async fn register(
+    http: &Http,
+    commands: &[poise::Command<Data, Error>],
+    config: &Config,
+) -> Result<(), serenity::Error> {
+    let general = &config.general;
+    if general.dev_guilds.is_empty() {
+        return poise::builtins::register_globally(http, commands).await;
+    }
+    for guild in &general.dev_guilds {
+        poise::builtins::register_in_guild(http, commands, GuildId::new(*guild)).await?;
+    }
+    if general.clear_global_commands {
+        Command::set_global_commands(http, vec![]).await?;
+    }
+    Ok(())
+}
+
+/// Restricts a command to the users listed in `privileged.allowed_users`.
+pub async fn is_privileged(ctx: Context<'_>) -> Result<bool, Error> {
+    let allowed = CONFIG
+        .get()
+        .unwrap()
+        .privileged
+        .allowed_users
+        .contains(&ctx.author().id.get());
+    if !allowed {
+        send_reply(
+            &ctx,
+            error_reply(
+                Some(ctx.serenity_context()),
+                "You are not allowed to use this command.".to_string(),
+                None,
+            )
+            .await
+            .ephemeral(true),
+        )
+        .await;
+    }
+    Ok(allowed)
+}
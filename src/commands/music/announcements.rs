@@ -0,0 +1,177 @@
+use super::{TRACK_METADATA, settings, track_link};
+use crate::commands::{Context, Error};
+use crate::utils::message::{error_reply, info_embed, info_reply, send_message, send_reply};
+use crate::utils::time::format_duration;
+use serenity::all::{
+    ChannelId, ChannelType, CreateEmbed, CreateMessage, GuildChannel, GuildId, Http, Mentionable,
+};
+use songbird::Songbird;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Announces a track that started playing, in the guild's announce channel or else the
+/// channel it was requested in.
+pub async fn announce_track(
+    http: &Http,
+    songbird: &Arc<Songbird>,
+    guild_id: GuildId,
+    origin: ChannelId,
+    uuid: Uuid,
+) {
+    if let Some((channel_id, embed)) = announcement(songbird, guild_id, origin, uuid).await {
+        send_message(http, &channel_id, CreateMessage::new().embed(embed)).await;
+    }
+}
+
+/// The channel a track that started playing is announced in and the embed announcing it,
+/// `None` when it isn't announced.
+pub async fn announcement(
+    songbird: &Arc<Songbird>,
+    guild_id: GuildId,
+    origin: ChannelId,
+    uuid: Uuid,
+) -> Option<(ChannelId, CreateEmbed)> {
+    let settings = settings::get(guild_id).await;
+    if settings.announcements_disabled {
+        return None;
+    }
+    let channel_id = match settings.announce_channel {
+        Some(channel) => ChannelId::new(channel),
+        // The request channel already shows the current track on its panel.
+        None if settings.request_channel == Some(origin.get()) => return None,
+        None => origin,
+    };
+    let track = TRACK_METADATA.lock().await.get(&uuid).cloned()?;
+    let position = match songbird.get(guild_id) {
+        Some(handler_lock) => {
+            let queue = handler_lock.lock().await.queue().current_queue();
+            queue
+                .iter()
+                .position(|queued| queued.uuid() == uuid)
+                .map(|index| (index + 1, queue.len()))
+        }
+        None => None,
+    };
+    let mut embed = info_embed(
+        None,
+        Some("Now playing".to_string()),
+        Some(track_link(&track.metadata)),
+    )
+    .await;
+    if let Some(thumbnail) = &track.metadata.thumbnail {
+        embed = embed.thumbnail(thumbnail);
+    }
+    if let Some(duration) = track.metadata.duration {
+        embed = embed.field("Duration", format_duration(duration), true);
+    }
+    embed = embed.field("Requested by", track.requester.mention().to_string(), true);
+    if let Some((position, length)) = position {
+        embed = embed.field("Position", format!("{} of {}", position, length), true);
+    }
+    Some((channel_id, embed))
+}
+
+async fn reply(ctx: &Context<'_>, content: String, is_error: bool) {
+    let reply = if is_error {
+        error_reply(
+            Some(ctx.serenity_context()),
+            content,
+            Some("Music".to_string()),
+        )
+        .await
+    } else {
+        info_reply(
+            Some(ctx.serenity_context()),
+            content,
+            Some("Music".to_string()),
+        )
+        .await
+    };
+    send_reply(ctx, reply).await;
+}
+
+/// Configures the messages announcing each track
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD",
+    subcommands("channel", "enable", "disable"),
+    subcommand_required
+)]
+pub async fn announcements(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Sets the channel tracks are announced in, or the one they're requested in if omitted
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn channel(
+    ctx: Context<'_>,
+    #[description = "The channel to announce tracks in"]
+    #[channel_types("Text")]
+    channel: Option<GuildChannel>,
+) -> Result<(), Error> {
+    if let Some(channel) = &channel
+        && channel.kind != ChannelType::Text
+    {
+        reply(
+            &ctx,
+            "Tracks can only be announced in a text channel.".to_string(),
+            true,
+        )
+        .await;
+        return Ok(());
+    }
+    settings::update(ctx.guild_id().unwrap(), |settings| {
+        settings.announce_channel = channel.as_ref().map(|channel| channel.id.get());
+    })
+    .await;
+    let content = match channel {
+        Some(channel) => format!("Tracks will be announced in {}.", channel),
+        None => "Tracks will be announced where they were requested.".to_string(),
+    };
+    reply(&ctx, content, false).await;
+    Ok(())
+}
+
+/// Announces each track when it starts playing
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn enable(ctx: Context<'_>) -> Result<(), Error> {
+    settings::update(ctx.guild_id().unwrap(), |settings| {
+        settings.announcements_disabled = false;
+    })
+    .await;
+    reply(&ctx, "Tracks will be announced again.".to_string(), false).await;
+    Ok(())
+}
+
+/// Stops announcing tracks when they start playing
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn disable(ctx: Context<'_>) -> Result<(), Error> {
+    settings::update(ctx.guild_id().unwrap(), |settings| {
+        settings.announcements_disabled = true;
+    })
+    .await;
+    reply(
+        &ctx,
+        "Tracks won't be announced anymore.".to_string(),
+        false,
+    )
+    .await;
+    Ok(())
+}
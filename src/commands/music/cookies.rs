@@ -0,0 +1,271 @@
+use crate::CONFIG;
+use crate::commands::{Context, Error, is_privileged};
+use crate::config::Config;
+use crate::utils::message::{error_reply, info_reply, send_reply};
+use chrono::Utc;
+use serenity::all::Attachment;
+use std::fs;
+use std::path::Path;
+use tracing::{error, info, warn};
+
+const MAX_COOKIES_SIZE: u32 = 1024 * 1024;
+/// Cookies YouTube needs for a signed in session.
+const IMPORTANT_COOKIES: [&str; 7] = [
+    "SID",
+    "HSID",
+    "SSID",
+    "APISID",
+    "SAPISID",
+    "__Secure-3PSID",
+    "LOGIN_INFO",
+];
+
+#[derive(Debug)]
+pub struct Cookie {
+    pub domain: String,
+    pub name: String,
+    /// Unix timestamp, 0 for session cookies.
+    pub expires: i64,
+}
+
+pub struct CookieReport {
+    pub count: usize,
+    pub expired: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+impl CookieReport {
+    fn summary(&self) -> String {
+        let mut summary = format!("Loaded {} cookies.", self.count);
+        if !self.expired.is_empty() {
+            summary.push_str(&format!("\nExpired: `{}`", self.expired.join("`, `")));
+        }
+        if !self.missing.is_empty() {
+            summary.push_str(&format!("\nMissing: `{}`", self.missing.join("`, `")));
+        }
+        summary
+    }
+}
+
+/// Parses a Netscape (`cookies.txt`) cookie file.
+pub fn parse(content: &str) -> Result<Vec<Cookie>, String> {
+    let mut cookies = vec![];
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        // HttpOnly cookies are prefixed with a marker that looks like a comment.
+        let line = line.strip_prefix("#HttpOnly_").unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(format!(
+                "line {}: expected 7 tab-separated fields, found {}",
+                index + 1,
+                fields.len()
+            ));
+        }
+        let expires = fields[4]
+            .parse::<i64>()
+            .map_err(|_| format!("line {}: invalid expiry '{}'", index + 1, fields[4]))?;
+        cookies.push(Cookie {
+            domain: fields[0].to_string(),
+            name: fields[5].to_string(),
+            expires,
+        });
+    }
+    if cookies.is_empty() {
+        return Err("no cookies found".to_string());
+    }
+    Ok(cookies)
+}
+
+/// Checks the important YouTube cookies for expiry.
+pub fn report(cookies: &[Cookie]) -> CookieReport {
+    let now = Utc::now().timestamp();
+    let mut expired = vec![];
+    let mut missing = vec![];
+    for name in IMPORTANT_COOKIES {
+        let found: Vec<&Cookie> = cookies
+            .iter()
+            .filter(|cookie| cookie.name == name && cookie.domain.ends_with("youtube.com"))
+            .collect();
+        if found.is_empty() {
+            missing.push(name.to_string());
+        } else if found
+            .iter()
+            .all(|cookie| cookie.expires != 0 && cookie.expires < now)
+        {
+            expired.push(name.to_string());
+        }
+    }
+    CookieReport {
+        count: cookies.len(),
+        expired,
+        missing,
+    }
+}
+
+/// Reads and validates the cookie file at `path`.
+pub fn load(path: &str) -> Result<CookieReport, String> {
+    if !Path::new(path).exists() {
+        return Err(format!("'{}' does not exist", path));
+    }
+    let content =
+        fs::read_to_string(path).map_err(|why| format!("failed to read '{}': {}", path, why))?;
+    parse(&content).map(|cookies| report(&cookies))
+}
+
+/// Validates the cookie file on startup, only logging since yt-dlp can still run without it.
+pub fn check(config: &Config) {
+    let path = &config.features.music_player.workarounds.ytdl_cookies_path;
+    match load(path) {
+        Ok(report) => {
+            info!("Loaded {} cookies from '{}'", report.count, path);
+            if !report.expired.is_empty() {
+                warn!(
+                    "Cookies in '{}' have expired: {}",
+                    path,
+                    report.expired.join(", ")
+                );
+            }
+            if !report.missing.is_empty() {
+                warn!(
+                    "Cookies in '{}' are missing: {}",
+                    path,
+                    report.missing.join(", ")
+                );
+            }
+        }
+        Err(why) => error!("Invalid cookie file: {}", why),
+    }
+}
+
+/// Shows the cookie file status or replaces it with an uploaded one
+#[poise::command(slash_command, ephemeral, check = "is_privileged")]
+pub async fn cookies(
+    ctx: Context<'_>,
+    #[description = "A Netscape formatted cookies.txt to replace the current one"]
+    file: Option<Attachment>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+    let workarounds = &CONFIG.get().unwrap().features.music_player.workarounds;
+    let path = &workarounds.ytdl_cookies_path;
+    if let Some(file) = file {
+        if file.size > MAX_COOKIES_SIZE {
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    "The cookie file is too large.".to_string(),
+                    Some("Cookies".to_string()),
+                )
+                .await,
+            )
+            .await;
+            return Ok(());
+        }
+        let content = String::from_utf8(file.download().await?)
+            .map_err(|_| "it isn't UTF-8 text".to_string())
+            .and_then(|content| parse(&content).map(|_| content));
+        let content = match content {
+            Ok(content) => content,
+            Err(why) => {
+                send_reply(
+                    &ctx,
+                    error_reply(
+                        Some(ctx.serenity_context()),
+                        format!("The uploaded file is not a valid cookie file: {}", why),
+                        Some("Cookies".to_string()),
+                    )
+                    .await,
+                )
+                .await;
+                return Ok(());
+            }
+        };
+        // Write to a temporary file first so yt-dlp never reads a partially written one.
+        let temp_path = format!("{}.tmp", path);
+        fs::write(&temp_path, content)?;
+        if Path::new(path).exists() {
+            fs::copy(path, format!("{}.bak", path))?;
+        }
+        fs::rename(&temp_path, path)?;
+        info!(
+            "Cookie file replaced by {} ({})",
+            ctx.author().name,
+            ctx.author().id
+        );
+    }
+    let reply = match load(path) {
+        Ok(report) => {
+            let mut summary = report.summary();
+            if !workarounds.ytdl_use_cookies {
+                summary
+                    .push_str("\n\n-# Cookies are disabled in the config, yt-dlp won't use them.");
+            }
+            info_reply(
+                Some(ctx.serenity_context()),
+                summary,
+                Some("Cookies".to_string()),
+            )
+            .await
+        }
+        Err(why) => {
+            error_reply(
+                Some(ctx.serenity_context()),
+                format!("Invalid cookie file: {}", why),
+                Some("Cookies".to_string()),
+            )
+            .await
+        }
+    };
+    send_reply(&ctx, reply).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FILE: &str = "# Netscape HTTP Cookie File\n\
+        # This is a generated file! Do not edit.\n\
+        \n\
+        .youtube.com\tTRUE\t/\tTRUE\t0\tPREF\tf6=40000000\r\n\
+        #HttpOnly_.youtube.com\tTRUE\t/\tTRUE\t4102444800\tHSID\tAbC\n\
+        .youtube.com\tTRUE\t/\tTRUE\t1000\tSID\tdef\n\
+        .google.com\tTRUE\t/\tTRUE\t4102444800\tSSID\tghi\n";
+
+    #[test]
+    fn parses_netscape_files() {
+        let cookies = parse(FILE).unwrap();
+        let names: Vec<&str> = cookies.iter().map(|cookie| cookie.name.as_str()).collect();
+        assert_eq!(names, ["PREF", "HSID", "SID", "SSID"]);
+        assert_eq!(cookies[0].expires, 0);
+        // The HttpOnly marker isn't part of the domain.
+        assert_eq!(cookies[1].domain, ".youtube.com");
+        assert_eq!(cookies[1].expires, 4102444800);
+    }
+
+    #[test]
+    fn rejects_malformed_files() {
+        assert!(parse("").is_err());
+        assert!(parse("# only comments\n").is_err());
+        let error = parse(".youtube.com\tTRUE\t/\tTRUE\t0\tPREF\n").unwrap_err();
+        assert!(error.starts_with("line 1:"), "{}", error);
+        let error = parse("# header\n.youtube.com\tTRUE\t/\tTRUE\tsoon\tSID\tx\n").unwrap_err();
+        assert!(error.contains("line 2: invalid expiry 'soon'"), "{}", error);
+    }
+
+    #[test]
+    fn reports_expired_and_missing_cookies() {
+        let report = report(&parse(FILE).unwrap());
+        assert_eq!(report.count, 4);
+        assert_eq!(report.expired, ["SID"]);
+        // SSID is only set for google.com, HSID hasn't expired.
+        assert!(report.missing.contains(&"SSID".to_string()));
+        assert!(!report.missing.contains(&"HSID".to_string()));
+        assert!(!report.missing.contains(&"SID".to_string()));
+        assert_eq!(report.missing.len(), IMPORTANT_COOKIES.len() - 2);
+    }
+}
@@ -0,0 +1,218 @@
+use super::{
+    QueuedTrack, Request, add_to_queue, join_if_not_in_vc, notify_if_queue_full, play_query,
+    query_track, skip_current,
+};
+use crate::CONFIG;
+use crate::commands::{Context, Error};
+use crate::storage::{self, BackgroundSaver};
+use crate::utils::message::{error_reply, info_reply, send_reply};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serenity::all::{GuildId, UserId};
+use songbird::tracks::TrackState;
+use std::collections::{HashMap, VecDeque};
+use std::sync::LazyLock;
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+const HISTORY_FILE: &str = "history";
+const PAGE_SIZE: usize = 10;
+
+/// Played tracks by guild, most recent first.
+static HISTORY: LazyLock<Mutex<HashMap<u64, VecDeque<HistoryEntry>>>> =
+    LazyLock::new(|| Mutex::new(storage::load(HISTORY_FILE)));
+static SAVER: BackgroundSaver = BackgroundSaver::new(HISTORY_FILE);
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct HistoryEntry {
+    pub title: String,
+    pub url: Option<String>,
+    pub requester: u64,
+    /// Unix timestamp of when the track started playing.
+    pub played_at: i64,
+    pub skipped: bool,
+}
+
+impl HistoryEntry {
+    /// Query that queues the track again.
+    fn query(&self) -> String {
+        self.url.clone().unwrap_or_else(|| self.title.clone())
+    }
+
+    fn link(&self) -> String {
+        match &self.url {
+            Some(url) if url.starts_with("http") => format!("[{}]({})", self.title, url),
+            _ => self.title.clone(),
+        }
+    }
+}
+
+/// Adds a track that just ended to the history of its guild.
+pub(super) async fn record(guild_id: GuildId, track: QueuedTrack, state: &TrackState) {
+    // Tracks removed from the queue before their turn were never heard.
+    if state.play_time.is_zero() {
+        return;
+    }
+    let skipped = track.skipped;
+    let entry = HistoryEntry {
+        title: track
+            .metadata
+            .title
+            .unwrap_or_else(|| "Unknown track".to_string()),
+        url: track.metadata.source_url,
+        requester: track.requester.get(),
+        played_at: Utc::now().timestamp() - state.play_time.as_secs() as i64,
+        skipped,
+    };
+    let history_length = CONFIG
+        .get()
+        .unwrap()
+        .features
+        .music_player
+        .queue
+        .history_length;
+    let mut history = HISTORY.lock().await;
+    let entries = history.entry(guild_id.get()).or_default();
+    entries.push_front(entry);
+    entries.truncate(history_length);
+    SAVER.save(history.clone());
+}
+
+/// Removes the tracks requested by a user from every guild's history.
+pub async fn forget_user(user_id: UserId) {
+    let mut history = HISTORY.lock().await;
+    for entries in history.values_mut() {
+        entries.retain(|entry| entry.requester != user_id.get());
+    }
+    history.retain(|_, entries| !entries.is_empty());
+    SAVER.save(history.clone());
+}
+
+async fn entry(guild_id: GuildId, index: usize) -> Option<HistoryEntry> {
+    HISTORY
+        .lock()
+        .await
+        .get(&guild_id.get())
+        .and_then(|entries| entries.get(index))
+        .cloned()
+}
+
+async fn reply(ctx: &Context<'_>, content: String, is_error: bool) {
+    let reply = if is_error {
+        error_reply(
+            Some(ctx.serenity_context()),
+            content,
+            Some("History".to_string()),
+        )
+        .await
+    } else {
+        info_reply(
+            Some(ctx.serenity_context()),
+            content,
+            Some("History".to_string()),
+        )
+        .await
+    };
+    send_reply(ctx, reply).await;
+}
+
+/// Shows the recently played tracks
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn history(
+    ctx: Context<'_>,
+    #[description = "The page to show, starting at 1"]
+    #[min = 1]
+    page: Option<usize>,
+) -> Result<(), Error> {
+    let history = HISTORY.lock().await;
+    let entries = history.get(&ctx.guild_id().unwrap().get());
+    let total = entries.map(|entries| entries.len()).unwrap_or(0);
+    let pages = total.div_ceil(PAGE_SIZE).max(1);
+    let page = page.unwrap_or(1).clamp(1, pages);
+    let mut list = "## History\n".to_string();
+    if total == 0 {
+        list.push_str("Nothing has been played yet.");
+    }
+    for (index, entry) in entries
+        .into_iter()
+        .flatten()
+        .enumerate()
+        .skip((page - 1) * PAGE_SIZE)
+        .take(PAGE_SIZE)
+    {
+        list.push_str(&format!(
+            "{}. {} by <@{}> <t:{}:R>{}\n",
+            index + 1,
+            entry.link(),
+            entry.requester,
+            entry.played_at,
+            if entry.skipped { " (skipped)" } else { "" }
+        ));
+    }
+    if pages > 1 {
+        list.push_str(&format!("\n-# Page {}/{}", page, pages));
+    }
+    drop(history);
+    reply(&ctx, list, false).await;
+    Ok(())
+}
+
+/// Queues a track from the history again
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn replay(
+    ctx: Context<'_>,
+    #[description = "The number of the track in /history"]
+    #[min = 1]
+    number: usize,
+) -> Result<(), Error> {
+    let Some(entry) = entry(ctx.guild_id().unwrap(), number.saturating_sub(1)).await else {
+        reply(
+            &ctx,
+            format!("There's no track {} in the history.", number),
+            true,
+        )
+        .await;
+        return Ok(());
+    };
+    play_query(ctx, entry.query(), None).await
+}
+
+/// Goes back to the last played track
+#[poise::command(slash_command, prefix_command, guild_only, aliases("back"))]
+pub async fn previous(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+    let guild_id = ctx.guild_id().unwrap();
+    let Some(entry) = entry(guild_id, 0).await else {
+        reply(&ctx, "Nothing has been played yet.".to_string(), true).await;
+        return Ok(());
+    };
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if !join_if_not_in_vc(ctx, &manager).await || notify_if_queue_full(&ctx, &manager).await {
+        return Ok(());
+    }
+    let track = match query_track(entry.query(), None).await {
+        Ok(track) => track,
+        Err(why) => {
+            debug!("Failed to resolve previous track: {}", why);
+            reply(&ctx, why.user_message(), true).await;
+            return Ok(());
+        }
+    };
+    let (song, playing_now) = add_to_queue(&Request::from_ctx(&ctx), &manager, track).await;
+    if !playing_now {
+        // Move the track right after the current one, then skip to it.
+        let handler_lock = manager.get(guild_id).unwrap();
+        let handler = handler_lock.lock().await;
+        handler.queue().modify_queue(|queue| {
+            if let Some(index) = queue.iter().position(|queued| queued.uuid() == song.uuid()) {
+                let queued = queue.remove(index).unwrap();
+                queue.insert(1, queued);
+            }
+        });
+        if let Err(why) = skip_current(handler.queue()).await {
+            error!("Failed to skip to the previous track: {:?}", why);
+        }
+    }
+    reply(&ctx, format!("Going back to {}.", entry.link()), false).await;
+    Ok(())
+}
@@ -0,0 +1,468 @@
+use super::source::LocalResolver;
+use super::{TRACK_METADATA, get_http_client};
+use crate::CONFIG;
+use crate::commands::{Context, Error};
+use crate::utils::message::{error_reply, info_reply, send_reply};
+use serenity::all::{CreateEmbed, EditMessage, Http, Message};
+use serenity::async_trait;
+use songbird::input::AuxMetadata;
+use songbird::tracks::TrackHandle;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+/// Longest lyrics shown at once, leaving room in the embed description.
+const MAX_LENGTH: usize = 4000;
+/// Lines shown before and after the current one of synced lyrics.
+const CONTEXT_BEFORE: usize = 2;
+const CONTEXT_AFTER: usize = 3;
+
+/// Providers in the order they're asked for lyrics.
+static PROVIDERS: LazyLock<Vec<Box<dyn LyricsProvider>>> = LazyLock::new(|| {
+    let mut providers: Vec<Box<dyn LyricsProvider>> = vec![Box::new(LocalLyricsProvider)];
+    if CONFIG.get().unwrap().features.music_player.lyrics.lrclib {
+        providers.push(Box::new(LrclibProvider));
+    }
+    providers
+});
+
+pub struct LyricLine {
+    pub time: Duration,
+    pub text: String,
+}
+
+pub enum Lyrics {
+    Plain(String),
+    /// Lines sorted by the time they're sung at.
+    Synced(Vec<LyricLine>),
+}
+
+impl Lyrics {
+    /// Reads LRC formatted lyrics, or plain ones when there are no timestamps.
+    pub fn parse(content: &str) -> Lyrics {
+        match parse_lrc(content) {
+            Some(lines) => Lyrics::Synced(lines),
+            None => Lyrics::Plain(content.trim().to_string()),
+        }
+    }
+}
+
+/// Finds the lyrics of a track.
+#[async_trait]
+pub trait LyricsProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Returns `Ok(None)` when the provider doesn't know the track.
+    async fn fetch(&self, metadata: &AuxMetadata) -> Result<Option<Lyrics>, String>;
+}
+
+/// Parses a `mm:ss.xx` timestamp, the lyrics can come from anywhere so nothing is trusted.
+fn parse_timestamp(timestamp: &str) -> Option<Duration> {
+    let (minutes, seconds) = timestamp.split_once(':')?;
+    let minutes = minutes.trim().parse::<u64>().ok()?;
+    let seconds = seconds.trim().parse::<f64>().ok()?;
+    Duration::from_secs(minutes.checked_mul(60)?)
+        .checked_add(Duration::try_from_secs_f64(seconds).ok()?)
+}
+
+/// Parses LRC lyrics, returning `None` if no line has a timestamp.
+pub fn parse_lrc(content: &str) -> Option<Vec<LyricLine>> {
+    let mut lines = vec![];
+    // Milliseconds the lyrics should be shown earlier.
+    let mut offset: i64 = 0;
+    for line in content.lines() {
+        let mut rest = line.trim();
+        let mut times = vec![];
+        // A line can be sung several times, `[00:12.00][01:30.00]text`.
+        while let Some(tag) = rest.strip_prefix('[') {
+            let Some((tag, after)) = tag.split_once(']') else {
+                break;
+            };
+            if let Some(value) = tag.strip_prefix("offset:") {
+                offset = value.trim().parse().unwrap_or(0);
+            } else if let Some(time) = parse_timestamp(tag) {
+                times.push(time);
+            }
+            rest = after;
+        }
+        for time in times {
+            let time = if offset >= 0 {
+                time.saturating_sub(Duration::from_millis(offset as u64))
+            } else {
+                time.saturating_add(Duration::from_millis(offset.unsigned_abs()))
+            };
+            lines.push(LyricLine {
+                time,
+                text: rest.trim().to_string(),
+            });
+        }
+    }
+    if lines.is_empty() {
+        return None;
+    }
+    lines.sort_by_key(|line| line.time);
+    Some(lines)
+}
+
+/// Reads a `.lrc` or `.txt` file next to a track of the local library.
+pub struct LocalLyricsProvider;
+
+#[async_trait]
+impl LyricsProvider for LocalLyricsProvider {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn fetch(&self, metadata: &AuxMetadata) -> Result<Option<Lyrics>, String> {
+        let Some(query) = metadata
+            .source_url
+            .as_deref()
+            .filter(|url| url.starts_with("file://"))
+        else {
+            return Ok(None);
+        };
+        let Ok(path) = LocalResolver::library_path(query) else {
+            return Ok(None);
+        };
+        for extension in ["lrc", "txt"] {
+            let lyrics_path = path.with_extension(extension);
+            match tokio::fs::read_to_string(&lyrics_path).await {
+                Ok(content) => return Ok(Some(Lyrics::parse(&content))),
+                Err(why) if why.kind() == std::io::ErrorKind::NotFound => continue,
+                Err(why) => return Err(format!("failed to read {:?}: {}", lyrics_path, why)),
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Looks lyrics up on lrclib.net.
+pub struct LrclibProvider;
+
+impl LrclibProvider {
+    async fn search(query: &[(&str, &str)]) -> Result<Vec<serde_json::Value>, String> {
+        let response = get_http_client()
+            .await
+            .get("https://lrclib.net/api/search")
+            .query(query)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|why| why.to_string())?;
+        let body = response.bytes().await.map_err(|why| why.to_string())?;
+        serde_json::from_slice(&body).map_err(|why| why.to_string())
+    }
+}
+
+#[async_trait]
+impl LyricsProvider for LrclibProvider {
+    fn name(&self) -> &'static str {
+        "lrclib"
+    }
+
+    async fn fetch(&self, metadata: &AuxMetadata) -> Result<Option<Lyrics>, String> {
+        let Some(title) = metadata.track.as_ref().or(metadata.title.as_ref()) else {
+            return Ok(None);
+        };
+        let mut results = vec![];
+        if let Some(artist) = &metadata.artist {
+            results = Self::search(&[("track_name", title), ("artist_name", artist)]).await?;
+        }
+        // The artist of videos is often just the uploader, search by title alone then.
+        if results.is_empty() {
+            results = Self::search(&[("q", title)]).await?;
+        }
+        // Prefer the result closest to the track's duration, lyrics of other versions drift.
+        let duration = metadata.duration.map(|duration| duration.as_secs_f64());
+        let best = results
+            .iter()
+            .filter(|result| !result["instrumental"].as_bool().unwrap_or(false))
+            .min_by_key(|result| match (duration, result["duration"].as_f64()) {
+                (Some(duration), Some(other)) => (duration - other).abs() as u64,
+                _ => u64::MAX,
+            });
+        let Some(best) = best else {
+            return Ok(None);
+        };
+        if let Some(synced) = best["syncedLyrics"].as_str()
+            && let Some(lines) = parse_lrc(synced)
+        {
+            return Ok(Some(Lyrics::Synced(lines)));
+        }
+        Ok(best["plainLyrics"]
+            .as_str()
+            .filter(|plain| !plain.trim().is_empty())
+            .map(|plain| Lyrics::Plain(plain.trim().to_string())))
+    }
+}
+
+async fn find_lyrics(metadata: &AuxMetadata) -> Option<Lyrics> {
+    find_with(&PROVIDERS, metadata).await
+}
+
+/// Asks the providers in order, skipping the ones that fail.
+async fn find_with(
+    providers: &[Box<dyn LyricsProvider>],
+    metadata: &AuxMetadata,
+) -> Option<Lyrics> {
+    for provider in providers {
+        match provider.fetch(metadata).await {
+            Ok(Some(lyrics)) => {
+                debug!("Found lyrics with the {} provider", provider.name());
+                return Some(lyrics);
+            }
+            Ok(None) => {}
+            Err(why) => warn!("Lyrics provider {} failed: {}", provider.name(), why),
+        }
+    }
+    None
+}
+
+/// Shows the lines around the one being sung at `position`, with the current one in bold.
+fn synced_window(lines: &[LyricLine], position: Duration) -> (Option<usize>, String) {
+    let current = lines.iter().rposition(|line| line.time <= position);
+    let start = current.unwrap_or(0).saturating_sub(CONTEXT_BEFORE);
+    let end = (current.unwrap_or(0) + CONTEXT_AFTER + 1).min(lines.len());
+    let mut window = String::new();
+    for (index, line) in lines.iter().enumerate().take(end).skip(start) {
+        let text = if line.text.is_empty() {
+            "♪"
+        } else {
+            &line.text
+        };
+        if Some(index) == current {
+            window.push_str(&format!("**{}**\n", text));
+        } else {
+            window.push_str(&format!("-# {}\n", text));
+        }
+    }
+    (current, window)
+}
+
+/// Keeps the lyrics message on the line being sung until the track ends.
+fn follow(track: TrackHandle, lines: Vec<LyricLine>, mut message: Message, http: Arc<Http>) {
+    let interval = Duration::from_secs(
+        CONFIG
+            .get()
+            .unwrap()
+            .features
+            .music_player
+            .lyrics
+            .update_interval_secs
+            .max(1),
+    );
+    tokio::spawn(async move {
+        let mut shown = None;
+        loop {
+            sleep(interval).await;
+            let state = match track.get_info().await {
+                Ok(state) if !state.playing.is_done() => state,
+                _ => return,
+            };
+            let (current, window) = synced_window(&lines, state.position);
+            if current == shown {
+                continue;
+            }
+            shown = current;
+            let Some(embed) = message.embeds.first().cloned() else {
+                return;
+            };
+            let embed = CreateEmbed::from(embed).description(window);
+            if let Err(why) = message.edit(&http, EditMessage::new().embed(embed)).await {
+                // The message was most likely deleted.
+                debug!("Stopped following lyrics: {:?}", why);
+                return;
+            }
+        }
+    });
+}
+
+/// Shows the lyrics of the current track
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn lyrics(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    let song = match manager.get(ctx.guild_id().unwrap()) {
+        Some(handler_lock) => handler_lock.lock().await.queue().current(),
+        None => None,
+    };
+    let metadata = match &song {
+        Some(song) => TRACK_METADATA
+            .lock()
+            .await
+            .get(&song.uuid())
+            .map(|track| track.metadata.clone()),
+        None => None,
+    };
+    let (Some(song), Some(metadata)) = (song, metadata) else {
+        send_reply(
+            &ctx,
+            error_reply(
+                Some(ctx.serenity_context()),
+                "Nothing is playing right now.".to_string(),
+                Some("Lyrics".to_string()),
+            )
+            .await,
+        )
+        .await;
+        return Ok(());
+    };
+    let title = metadata
+        .title
+        .clone()
+        .unwrap_or_else(|| "Lyrics".to_string());
+    match find_lyrics(&metadata).await {
+        Some(Lyrics::Plain(mut text)) => {
+            if text.len() > MAX_LENGTH {
+                let end = text.floor_char_boundary(MAX_LENGTH);
+                text.truncate(end);
+                text.push_str("\n...");
+            }
+            send_reply(
+                &ctx,
+                info_reply(Some(ctx.serenity_context()), text, Some(title)).await,
+            )
+            .await;
+        }
+        Some(Lyrics::Synced(lines)) => {
+            let position = song
+                .get_info()
+                .await
+                .map(|state| state.position)
+                .unwrap_or_default();
+            let (_, window) = synced_window(&lines, position);
+            let handle = ctx
+                .send(info_reply(Some(ctx.serenity_context()), window, Some(title)).await)
+                .await?;
+            let message = handle.into_message().await?;
+            follow(song, lines, message, ctx.serenity_context().http.clone());
+        }
+        None => {
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("No lyrics were found for {}.", title),
+                    Some("Lyrics".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    fn times(lines: &[LyricLine]) -> Vec<u128> {
+        lines.iter().map(|line| line.time.as_millis()).collect()
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(
+            parse_timestamp("01:02.50"),
+            Some(Duration::from_millis(62_500))
+        );
+        assert_eq!(parse_timestamp("0:07"), Some(Duration::from_secs(7)));
+        assert_eq!(parse_timestamp(" 2 : 00 "), Some(Duration::from_secs(120)));
+    }
+
+    #[test]
+    fn rejects_malformed_timestamps() {
+        for timestamp in [
+            "00:-1",
+            "0:inf",
+            "0:NaN",
+            "0:1e30",
+            "-1:00",
+            "18446744073709551615:00",
+            "ar:Someone",
+            "12",
+            "",
+        ] {
+            assert_eq!(parse_timestamp(timestamp), None, "{}", timestamp);
+        }
+    }
+
+    #[test]
+    fn parses_lrc() {
+        let lines = parse_lrc(
+            "[ar:Artist]\n[ti:Title]\n[00:10.00]First\n[00:20.00][00:40.00]Chorus\n[00:30.00]\n",
+        )
+        .unwrap();
+        assert_eq!(times(&lines), [10_000, 20_000, 30_000, 40_000]);
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["First", "Chorus", "", "Chorus"]);
+    }
+
+    #[test]
+    fn applies_the_offset() {
+        let earlier = parse_lrc("[offset:500]\n[00:00.20]a\n[00:10.00]b").unwrap();
+        assert_eq!(times(&earlier), [0, 9_500]);
+        let later = parse_lrc("[offset:-500]\n[00:10.00]b").unwrap();
+        assert_eq!(times(&later), [10_500]);
+    }
+
+    #[test]
+    fn skips_malformed_lines() {
+        let lines =
+            parse_lrc("[00:-1]bad\n[0:inf]bad\n[0:NaN]bad\n[00:05.00]good\n[00:06").unwrap();
+        assert_eq!(times(&lines), [5_000]);
+        assert!(parse_lrc("[0:NaN]only bad\nplain text").is_none());
+        assert!(
+            matches!(Lyrics::parse("Just words\n"), Lyrics::Plain(text) if text == "Just words")
+        );
+    }
+
+    struct StubProvider {
+        name: &'static str,
+        result: fn() -> Result<Option<Lyrics>, String>,
+        calls: Arc<Mutex<Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl LyricsProvider for StubProvider {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        async fn fetch(&self, _metadata: &AuxMetadata) -> Result<Option<Lyrics>, String> {
+            self.calls.lock().unwrap().push(self.name);
+            (self.result)()
+        }
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_provider() {
+        let calls = Arc::new(Mutex::new(vec![]));
+        let stub = |name, result| -> Box<dyn LyricsProvider> {
+            Box::new(StubProvider {
+                name,
+                result,
+                calls: calls.clone(),
+            })
+        };
+        let providers = vec![
+            stub("failing", || Err("offline".to_string())),
+            stub("unknown", || Ok(None)),
+            stub("found", || Ok(Some(Lyrics::Plain("words".to_string())))),
+            stub("unused", || Ok(Some(Lyrics::Plain("other".to_string())))),
+        ];
+        let lyrics = find_with(&providers, &AuxMetadata::default()).await;
+        assert!(matches!(lyrics, Some(Lyrics::Plain(text)) if text == "words"));
+        assert_eq!(*calls.lock().unwrap(), ["failing", "unknown", "found"]);
+
+        let providers = vec![stub("unknown", || Ok(None))];
+        assert!(
+            find_with(&providers, &AuxMetadata::default())
+                .await
+                .is_none()
+        );
+    }
+}
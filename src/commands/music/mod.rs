@@ -0,0 +1,1249 @@
+use crate::CONFIG;
+use crate::commands::{Context, Error};
+use crate::metrics;
+use crate::utils::message::{error_reply, info_reply, send_message, send_reply};
+use futures::{StreamExt, stream};
+use poise::CreateReply;
+use reqwest::Client as HttpClient;
+use serenity::all::{
+    Attachment, Cache, ChannelId, CreateMessage, GuildChannel, GuildId, Http, Mentionable, UserId,
+};
+use serenity::async_trait;
+use serenity::client::Context as SerenityContext;
+use serenity::prelude::TypeMapKey;
+use songbird::error::TrackResult;
+use songbird::events::{Event, EventContext, EventHandler as VoiceEventHandler, TrackEvent};
+use songbird::input::AuxMetadata;
+use songbird::tracks::{TrackHandle, TrackQueue};
+use songbird::{Call, CoreEvent, Songbird};
+use source::{ResolvedTrack, SourceError};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use tokio::sync::{Mutex, MutexGuard};
+use tracing::{debug, error, trace, warn};
+use uuid::Uuid;
+
+pub mod announcements;
+pub mod cookies;
+pub mod history;
+pub mod lyrics;
+pub mod player;
+pub mod playlist;
+pub mod podcast;
+pub mod pot;
+pub mod radio;
+pub mod requests;
+pub mod settings;
+pub mod source;
+pub mod stats;
+pub mod ytdl;
+
+/// How many queries are resolved at once when enqueueing several of them.
+const RESOLVE_CONCURRENCY: usize = 4;
+/// Failed queries listed in a reply, the rest are only counted.
+const MAX_REPORTED_FAILURES: usize = 10;
+
+static HTTP_CLIENT: LazyLock<HttpClient> = LazyLock::new(HttpClient::new);
+static TRACK_METADATA: LazyLock<Mutex<HashMap<Uuid, QueuedTrack>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static VOICE_CHAT_PROPERTIES: LazyLock<Mutex<HashMap<songbird::id::ChannelId, VoiceChatProperties>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// What's known about a queued track besides its handle.
+#[derive(Clone)]
+struct QueuedTrack {
+    metadata: AuxMetadata,
+    requester: UserId,
+    /// Set when a user skipped the track, rather than it ending or the player stopping.
+    skipped: bool,
+}
+
+struct VoiceChatProperties {
+    volume: i8,
+}
+
+pub struct HttpKey;
+
+impl TypeMapKey for HttpKey {
+    type Value = HttpClient;
+}
+
+struct TrackStartNotifier {
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    http: Arc<Http>,
+    songbird: Arc<Songbird>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackStartNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(track_info) = ctx {
+            for (_, handle) in track_info.iter() {
+                announcements::announce_track(
+                    &self.http,
+                    &self.songbird,
+                    self.guild_id,
+                    self.channel_id,
+                    handle.uuid(),
+                )
+                .await;
+            }
+        }
+        // Tracks also "play" again when resumed, only announce them once.
+        Some(Event::Cancel)
+    }
+}
+
+struct TrackErrorNotifier;
+
+#[async_trait]
+impl VoiceEventHandler for TrackErrorNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(track_info) = ctx {
+            for (state, handle) in track_info.iter() {
+                metrics::track_failed();
+                warn!("Track {} failed: {:?}", handle.uuid(), state.playing);
+            }
+        }
+        None
+    }
+}
+
+struct UserDisconnectedNotifier {
+    vc: GuildChannel,
+    songbird: Arc<Songbird>,
+    cache: Arc<Cache>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for UserDisconnectedNotifier {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        let members = self.vc.members(self.cache.clone()).unwrap();
+        if members.len() == 1 {
+            let handler_lock = self.songbird.get(self.vc.guild_id).unwrap();
+            let mut handler = handler_lock.lock().await;
+            VOICE_CHAT_PROPERTIES.lock().await.remove(&handler.current_channel().unwrap());
+            handler.queue().stop();
+            handler.remove_all_global_events();
+            handler.leave().await.unwrap();
+        }
+        None
+    }
+}
+
+struct TrackEndNotifier {
+    guild_id: GuildId,
+}
+
+#[async_trait]
+impl VoiceEventHandler for TrackEndNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(track_info) = ctx {
+            // Remove the metadata from the map (since the track has ended)
+            let ended: Vec<_> = {
+                let mut metadatas = TRACK_METADATA.lock().await;
+                track_info
+                    .iter()
+                    .filter_map(|(state, handle)| {
+                        Some(((*state).clone(), metadatas.remove(&handle.uuid())?))
+                    })
+                    .collect()
+            };
+            for (state, track) in ended {
+                stats::record(self.guild_id, &track, &state).await;
+                history::record(self.guild_id, track, &state).await;
+            }
+        }
+        None
+    }
+}
+
+async fn in_vc(ctx: &Context<'_>, manager: &Arc<Songbird>) -> bool {
+    let handler_lock = manager.get(ctx.guild_id().unwrap());
+    return handler_lock.is_some()
+        && handler_lock
+            .unwrap()
+            .lock()
+            .await
+            .current_channel()
+            .is_some();
+}
+
+/// Who asked for something and where, so the player can be used outside of commands too.
+#[derive(Clone)]
+pub struct Request {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    pub serenity: SerenityContext,
+}
+
+impl Request {
+    fn from_ctx(ctx: &Context<'_>) -> Request {
+        Request {
+            guild_id: ctx.guild_id().unwrap(),
+            channel_id: ctx.channel_id(),
+            user_id: ctx.author().id,
+            serenity: ctx.serenity_context().clone(),
+        }
+    }
+}
+
+async fn join_vc(request: &Request, manager: Arc<Songbird>) -> Result<ChannelId, String> {
+    trace!("Joining VC...");
+    let guild_id = request.guild_id;
+    let channel_id = request.serenity.cache.guild(guild_id).and_then(|guild| {
+        guild
+            .voice_states
+            .get(&request.user_id)
+            .and_then(|voice_state| voice_state.channel_id)
+    });
+    let connect_to = match channel_id {
+        Some(channel) => channel,
+        None => {
+            return Err("User not in a voice channel.".to_string());
+        }
+    };
+    if let Ok(handler_lock) = manager.join(guild_id, connect_to).await {
+        let mut handler = handler_lock.lock().await;
+        VOICE_CHAT_PROPERTIES
+            .lock()
+            .await
+            .insert(connect_to.into(), VoiceChatProperties { volume: 100 });
+        // The call outlives leaving the channel, drop the handlers of the previous joins so
+        // they don't run twice.
+        handler.remove_all_global_events();
+        for event in [TrackEvent::Play, TrackEvent::Pause, TrackEvent::End] {
+            handler.add_global_event(
+                Event::Track(event),
+                player::PanelUpdater {
+                    guild_id,
+                    http: request.serenity.http.clone(),
+                    songbird: manager.clone(),
+                },
+            );
+        }
+        handler.add_global_event(Event::Track(TrackEvent::Error), TrackErrorNotifier);
+        handler.add_global_event(
+            Event::Core(CoreEvent::ClientDisconnect),
+            UserDisconnectedNotifier {
+                vc: guild_id
+                    .channels(&request.serenity.http)
+                    .await
+                    .unwrap()
+                    .get(&connect_to)
+                    .unwrap()
+                    .clone(),
+                cache: request.serenity.cache.clone(),
+                songbird: manager.clone(),
+            },
+        );
+        return Ok(channel_id.unwrap());
+    }
+    Err("Failed to join voice channel.".to_string())
+}
+
+/// Checks that the bot and the user are in a voice channel, the permission check of every
+/// player command and control panel button.
+async fn check_in_vc(
+    cache: &Cache,
+    manager: &Arc<Songbird>,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Result<(), &'static str> {
+    let bot_in_vc = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.current_channel().is_some(),
+        None => false,
+    };
+    if !bot_in_vc {
+        return Err("Not in a voice channel.");
+    }
+    let user_in_vc = cache.guild(guild_id).is_some_and(|guild| {
+        guild
+            .voice_states
+            .get(&user_id)
+            .and_then(|voice_state| voice_state.channel_id)
+            .is_some()
+    });
+    if !user_in_vc {
+        return Err("User not in a voice channel.");
+    }
+    Ok(())
+}
+
+async fn notify_if_not_vc(ctx: &Context<'_>, manager: &Arc<Songbird>) -> bool {
+    let check = check_in_vc(
+        &ctx.serenity_context().cache,
+        manager,
+        ctx.guild_id().unwrap(),
+        ctx.author().id,
+    )
+    .await;
+    if let Err(why) = check {
+        send_reply(
+            ctx,
+            error_reply(
+                Some(ctx.serenity_context()),
+                why.to_string(),
+                Some("Music".to_string()),
+            )
+            .await,
+        )
+        .await;
+        return true;
+    }
+    false
+}
+
+async fn notify_if_empty_queue(ctx: &Context<'_>, handler: &MutexGuard<'_, Call>) -> Option<TrackHandle> {
+    if handler.queue().is_empty() {
+        send_reply(
+            ctx,
+            error_reply(
+                Some(ctx.serenity_context()),
+                "No tracks are currently in queue.".to_string(),
+                Some("Music".to_string()),
+            )
+            .await,
+        )
+        .await;
+        return None;
+    }
+    handler.queue().current()
+}
+
+/// Sets the volume of every queued track and of the ones queued later in this call.
+async fn set_volume(handler: &Call, volume: i8) -> TrackResult<()> {
+    if let Some(channel) = handler.current_channel()
+        && let Some(properties) = VOICE_CHAT_PROPERTIES.lock().await.get_mut(&channel)
+    {
+        properties.volume = volume;
+    }
+    for song in handler.queue().current_queue() {
+        song.set_volume(volume as f32 / 100.0)?;
+    }
+    Ok(())
+}
+
+async fn get_http_client() -> HttpClient {
+    HTTP_CLIENT.clone()
+}
+
+/// Whether yt-dlp should use the PO token server, checking that it's reachable first.
+async fn use_pot() -> bool {
+    let workarounds = &CONFIG.get().unwrap().features.music_player.workarounds;
+    workarounds.ytdl_use_pot && pot::check(workarounds.ytdl_pot_server_port).await
+}
+
+/// Note appended to replies when the PO token server should be used but is down.
+fn pot_notice() -> &'static str {
+    let workarounds = &CONFIG.get().unwrap().features.music_player.workarounds;
+    if workarounds.ytdl_use_pot && !pot::is_healthy() {
+        "\n\n-# The PO token server is unavailable, the track was fetched without it."
+    } else {
+        ""
+    }
+}
+
+/// Resolves `query` through the source resolvers, picking one by `source` or the query itself.
+async fn query_track(query: String, source: Option<&str>) -> Result<ResolvedTrack, SourceError> {
+    source::resolve(source, &query).await
+}
+
+/// Formats a track as a markdown link, or just its title when it has no URL.
+fn track_link(metadata: &AuxMetadata) -> String {
+    let title = metadata.title.as_deref().unwrap_or("Unknown track");
+    match &metadata.source_url {
+        Some(url) if url.starts_with("http") => format!("[{}]({})", title, url),
+        _ => title.to_string(),
+    }
+}
+
+async fn autocomplete_source<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    source::names()
+        .into_iter()
+        .filter(move |name| name.starts_with(partial))
+        .map(|name| name.to_string())
+}
+
+/// Joins the voice channel of the user
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn join(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer().await?;
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    match join_vc(&Request::from_ctx(&ctx), manager).await {
+        Ok(channel_id) => {
+            send_reply(
+                &ctx,
+                info_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Joined {}", channel_id.mention()),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+        Err(why) => {
+            error!("Failed to join VC: {:?}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to join voice channel: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    };
+    Ok(())
+}
+
+/// Loops the current track
+#[poise::command(slash_command, prefix_command, guild_only, rename="loop")]
+pub async fn _loop(
+    ctx: Context<'_>,
+    #[description = "The amount of times to loop the track, 0 for infinite"]
+    times: Option<usize>,
+) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let handler = handler_lock.lock().await;
+    let song = notify_if_empty_queue(&ctx, &handler).await;
+    if song.is_none() {
+        return Ok(());
+    }
+    let song = song.unwrap();
+    let result: TrackResult<()> = match times {
+        None | Some(0) => song.enable_loop(),
+        Some(times) => song.loop_for(times),
+    };
+    match result {
+        Ok(_) => {
+            send_reply(
+                &ctx,
+                info_reply(
+                    Some(ctx.serenity_context()),
+                    "Looped the current track.".to_string(),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+        Err(why) => {
+            error!("Failed to loop track: {:?}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to loop track: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+/// Pauses the current track
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn pause(ctx: Context<'_>) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let handler = handler_lock.lock().await;
+    match handler.queue().pause() {
+        Ok(_) => {
+            send_reply(
+                &ctx,
+                info_reply(
+                    Some(ctx.serenity_context()),
+                    "Paused the current track.".to_string(),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+        Err(why) => {
+            error!("Failed to pause track: {:?}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to pause track: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+/// Unpauses the current track
+#[poise::command(slash_command, prefix_command, guild_only, aliases("unpause"))]
+pub async fn resume(ctx: Context<'_>) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let handler = handler_lock.lock().await;
+    match handler.queue().resume() {
+        Ok(_) => {
+            send_reply(
+                &ctx,
+                info_reply(
+                    Some(ctx.serenity_context()),
+                    "Unpaused the current track.".to_string(),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+        Err(why) => {
+            error!("Failed to unpause track: {:?}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to unpause track: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+/// Plays a track by url or query, or the tracks of an attached playlist file
+#[poise::command(slash_command, guild_only)]
+pub async fn play_slash(
+    ctx: Context<'_>,
+    #[description = "The track to play, can be url or query"] query: Option<String>,
+    #[description = "A .m3u, .pls or .txt file with one url or query per line"]
+    file: Option<Attachment>,
+    #[description = "Where to get the track from, detected from the query by default"]
+    #[autocomplete = "autocomplete_source"]
+    source: Option<String>,
+) -> Result<(), Error> {
+    play_request(ctx, query, file, source).await
+}
+
+/// Plays the attached playlist file if there's one, or the query otherwise.
+async fn play_request(
+    ctx: Context<'_>,
+    query: Option<String>,
+    file: Option<Attachment>,
+    source: Option<String>,
+) -> Result<(), Error> {
+    match (query, file) {
+        (_, Some(file)) => play_file(ctx, file, source).await,
+        (Some(query), None) => play_query(ctx, query, source).await,
+        (None, None) => {
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    "Give a track to play or attach a playlist file.".to_string(),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+            Ok(())
+        }
+    }
+}
+
+/// Plays a track by url or query, start with `source:<name>` to choose where it's from
+///
+/// Attach a .m3u, .pls or .txt file to play all of its tracks.
+#[poise::command(prefix_command, guild_only)]
+pub async fn play(
+    ctx: Context<'_>,
+    #[description = "The track to play, can be url or query"]
+    #[rest]
+    query: Option<String>,
+) -> Result<(), Error> {
+    let query = query.unwrap_or_default();
+    // Prefix commands can't have an optional argument before the rest of the message.
+    let (query, source) = match query
+        .strip_prefix("source:")
+        .map(|rest| rest.split_once(' ').unwrap_or((rest, "")))
+    {
+        Some((source, query)) => (query.trim().to_string(), Some(source.to_string())),
+        None => (query, None),
+    };
+    // Other attachments, like images, don't stop the query from being played.
+    let file = match ctx {
+        poise::Context::Prefix(prefix) => prefix
+            .msg
+            .attachments
+            .iter()
+            .find(|attachment| playlist::is_playlist_file(&attachment.filename))
+            .cloned(),
+        _ => None,
+    };
+    let query = Some(query).filter(|query| !query.is_empty());
+    play_request(ctx, query, file, source).await
+}
+
+/// `play` as a single command: the slash and prefix forms take different arguments so they're
+/// written separately, but poise only ever finds the first command with a given name.
+fn play_command() -> poise::Command<crate::commands::Data, Error> {
+    let slash = play_slash();
+    let mut command = play();
+    command.slash_action = slash.slash_action;
+    command.parameters = slash.parameters;
+    command.description = slash.description;
+    command
+}
+
+async fn play_query(ctx: Context<'_>, query: String, source: Option<String>) -> Result<(), Error> {
+    ctx.defer().await?;
+    debug!(
+        "Received command with query_or_url: {} by {} ({})",
+        query,
+        ctx.author().name,
+        ctx.author().id
+    );
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if !join_if_not_in_vc(ctx, &manager).await {
+        return Ok(());
+    }
+    if notify_if_queue_full(&ctx, &manager).await {
+        return Ok(());
+    }
+    trace!("Querying track...");
+    let track = match query_track(query, source.as_deref()).await {
+        Ok(track) => track,
+        Err(why) => {
+            debug!("Failed to resolve track: {}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    why.user_message(),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+            return Ok(());
+        }
+    };
+    enqueue_track(ctx, &manager, track).await;
+    Ok(())
+}
+
+/// Queues every entry of an attached playlist file, reporting the ones that failed.
+async fn play_file(ctx: Context<'_>, file: Attachment, source: Option<String>) -> Result<(), Error> {
+    ctx.defer().await?;
+    debug!(
+        "Received playlist file {} by {} ({})",
+        file.filename,
+        ctx.author().name,
+        ctx.author().id
+    );
+    if file.size > playlist::MAX_IMPORT_SIZE {
+        send_reply(
+            &ctx,
+            error_reply(
+                Some(ctx.serenity_context()),
+                "The playlist file is too large.".to_string(),
+                Some("Music".to_string()),
+            )
+            .await,
+        )
+        .await;
+        return Ok(());
+    }
+    let tracks = String::from_utf8(file.download().await?)
+        .map_err(|_| "the file isn't UTF-8 text".to_string())
+        .and_then(|content| playlist::parse_file(&file.filename, &content));
+    let tracks = match tracks {
+        Ok(tracks) if !tracks.is_empty() => tracks,
+        result => {
+            let why = result.err().unwrap_or_else(|| "no tracks found".to_string());
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to read the playlist file: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+            return Ok(());
+        }
+    };
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if !join_if_not_in_vc(ctx, &manager).await {
+        return Ok(());
+    }
+    let queries = tracks
+        .into_iter()
+        .map(|track| (track.query, track.source.or_else(|| source.clone())))
+        .collect();
+    let summary = enqueue_queries(ctx, &manager, queries).await;
+    let reply = format!(
+        "Added {} tracks from `{}` to the queue.{}{}",
+        summary.queued,
+        file.filename,
+        summary.details(),
+        pot_notice()
+    );
+    let reply = if summary.queued == 0 {
+        error_reply(
+            Some(ctx.serenity_context()),
+            reply,
+            Some("Music".to_string()),
+        )
+        .await
+    } else {
+        info_reply(
+            Some(ctx.serenity_context()),
+            reply,
+            Some("Music".to_string()),
+        )
+        .await
+    };
+    send_reply(&ctx, reply).await;
+    Ok(())
+}
+
+/// Joins the user's voice channel if the bot isn't in one, replying with the error on failure.
+async fn join_if_not_in_vc(ctx: Context<'_>, manager: &Arc<Songbird>) -> bool {
+    if in_vc(&ctx, manager).await {
+        return true;
+    }
+    if let Err(why) = join_vc(&Request::from_ctx(&ctx), manager.clone()).await {
+        error!("Failed to join VC: {:?}", why);
+        send_reply(
+            &ctx,
+            error_reply(
+                Some(ctx.serenity_context()),
+                format!("Failed to join voice channel: {}", why),
+                Some("Music".to_string()),
+            )
+            .await,
+        )
+        .await;
+        return false;
+    }
+    true
+}
+
+/// Enqueues a resolved track and registers its metadata, returning whether it's playing now.
+async fn add_to_queue(
+    request: &Request,
+    manager: &Arc<Songbird>,
+    track: ResolvedTrack,
+) -> (TrackHandle, bool) {
+    let (song, playing_now) = queue_track(request, manager, track).await;
+    if playing_now {
+        // The track may have started already, so it's announced right away.
+        announcements::announce_track(
+            &request.serenity.http,
+            manager,
+            request.guild_id,
+            request.channel_id,
+            song.uuid(),
+        )
+        .await;
+    }
+    (song, playing_now)
+}
+
+/// Like [`add_to_queue`], leaving the announcement of a track that's playing now to the caller.
+async fn queue_track(
+    request: &Request,
+    manager: &Arc<Songbird>,
+    track: ResolvedTrack,
+) -> (TrackHandle, bool) {
+    let ResolvedTrack {
+        input: src,
+        metadata,
+        live,
+    } = track;
+    let handler_lock = manager.get(request.guild_id).unwrap();
+    let mut handler = handler_lock.lock().await;
+    trace!("Enqueueing track...");
+    let song = handler.enqueue_input(src).await;
+    trace!("Enqueued track, setting volume...");
+    song.set_volume(
+        VOICE_CHAT_PROPERTIES.lock().await[&handler.current_channel().unwrap()].volume as f32
+            / 100.0,
+    )
+    .unwrap();
+    trace!("Got metadata, adding events...");
+    let _ = song.add_event(Event::Track(TrackEvent::End), TrackEndNotifier {
+        guild_id: request.guild_id,
+    });
+    TRACK_METADATA.lock().await.insert(
+        song.uuid(),
+        QueuedTrack {
+            metadata: metadata.clone(),
+            requester: request.user_id,
+            skipped: false,
+        },
+    );
+    if live && let Some(url) = &metadata.source_url {
+        radio::watch(
+            song.clone(),
+            url.clone(),
+            request.channel_id,
+            request.serenity.http.clone(),
+        );
+    }
+    let playing_now = handler.queue().len() == 1;
+    drop(handler);
+    if !playing_now {
+        let _ = song.add_event(Event::Track(TrackEvent::Play), TrackStartNotifier {
+            guild_id: request.guild_id,
+            channel_id: request.channel_id,
+            http: request.serenity.http.clone(),
+            songbird: manager.clone(),
+        });
+    }
+    (song, playing_now)
+}
+
+/// Enqueues a resolved track, registering its metadata and replying with what was queued.
+async fn enqueue_track(
+    ctx: Context<'_>,
+    manager: &Arc<Songbird>,
+    track: ResolvedTrack,
+) -> TrackHandle {
+    let metadata = track.metadata.clone();
+    let request = Request::from_ctx(&ctx);
+    let (song, playing) = queue_track(&request, manager, track).await;
+    if playing
+        && let Some((channel_id, embed)) =
+            announcements::announcement(manager, request.guild_id, request.channel_id, song.uuid())
+                .await
+    {
+        if channel_id == request.channel_id {
+            // The announcement answers the command instead of following a reply saying the same.
+            let mut reply = CreateReply::default().embed(embed).reply(true);
+            let notice = pot_notice().trim();
+            if !notice.is_empty() {
+                reply = reply.content(notice);
+            }
+            send_reply(&ctx, reply).await;
+            return song;
+        }
+        send_message(
+            &request.serenity.http,
+            &channel_id,
+            CreateMessage::new().embed(embed),
+        )
+        .await;
+    }
+    let reply = if playing {
+        format!("Playing track: {}{}", track_link(&metadata), pot_notice())
+    } else {
+        format!(
+            "Added track to queue: {}{}",
+            track_link(&metadata),
+            pot_notice()
+        )
+    };
+    send_reply(
+        &ctx,
+        info_reply(
+            Some(ctx.serenity_context()),
+            reply,
+            Some("Music".to_string()),
+        )
+        .await,
+    )
+    .await;
+    song
+}
+
+/// Outcome of enqueueing several queries at once.
+struct EnqueueSummary {
+    queued: usize,
+    failures: Vec<(String, SourceError)>,
+    /// Queries left out because the queue was full.
+    skipped: usize,
+}
+
+impl EnqueueSummary {
+    /// Lists the failed queries and the ones that didn't fit, to append to a reply.
+    fn details(&self) -> String {
+        let mut details = String::new();
+        for (query, why) in self.failures.iter().take(MAX_REPORTED_FAILURES) {
+            details.push_str(&format!("\n- `{}`: {}", query, why.user_message()));
+        }
+        if self.failures.len() > MAX_REPORTED_FAILURES {
+            details.push_str(&format!(
+                "\n- ...and {} more failed",
+                self.failures.len() - MAX_REPORTED_FAILURES
+            ));
+        }
+        if self.skipped > 0 {
+            details.push_str(&format!(
+                "\n\n-# The queue is full, {} tracks were left out.",
+                self.skipped
+            ));
+        }
+        details
+    }
+}
+
+/// How many more tracks the queue of the current server can take.
+async fn queue_space(manager: &Arc<Songbird>, guild_id: GuildId) -> usize {
+    let max_length = CONFIG.get().unwrap().features.music_player.queue.max_length;
+    let queued = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.queue().len(),
+        None => 0,
+    };
+    max_length.saturating_sub(queued)
+}
+
+/// Skips the current track, remembering that it was skipped for the history.
+async fn skip_current(queue: &TrackQueue) -> TrackResult<()> {
+    if let Some(current) = queue.current()
+        && let Some(track) = TRACK_METADATA.lock().await.get_mut(&current.uuid())
+    {
+        track.skipped = true;
+    }
+    queue.skip()
+}
+
+/// Replies that the queue is full when it can't take another track.
+async fn notify_if_queue_full(ctx: &Context<'_>, manager: &Arc<Songbird>) -> bool {
+    if queue_space(manager, ctx.guild_id().unwrap()).await > 0 {
+        return false;
+    }
+    send_reply(
+        ctx,
+        error_reply(
+            Some(ctx.serenity_context()),
+            "The queue is full.".to_string(),
+            Some("Music".to_string()),
+        )
+        .await,
+    )
+    .await;
+    true
+}
+
+/// Resolves several queries and enqueues them in order without replying for each of them,
+/// leaving out the ones that don't fit in the queue.
+async fn enqueue_queries(
+    ctx: Context<'_>,
+    manager: &Arc<Songbird>,
+    queries: Vec<(String, Option<String>)>,
+) -> EnqueueSummary {
+    let space = queue_space(manager, ctx.guild_id().unwrap()).await;
+    let total = queries.len();
+    let mut resolved = stream::iter(queries)
+        .map(|(query, source)| async move {
+            let result = query_track(query.clone(), source.as_deref()).await;
+            (query, result)
+        })
+        .buffered(RESOLVE_CONCURRENCY);
+    let request = Request::from_ctx(&ctx);
+    let mut queued = 0;
+    let mut failures = vec![];
+    // Failed queries don't take space, so resolving goes on until the queue is full.
+    while queued < space
+        && let Some((query, result)) = resolved.next().await
+    {
+        match result {
+            Ok(track) => {
+                add_to_queue(&request, manager, track).await;
+                queued += 1;
+            }
+            Err(why) => {
+                debug!("Failed to resolve {}: {}", query, why);
+                failures.push((query, why));
+            }
+        }
+    }
+    EnqueueSummary {
+        queued,
+        skipped: total - queued - failures.len(),
+        failures,
+    }
+}
+
+/// Shows the current queue
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn queue(ctx: Context<'_>) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let handler = handler_lock.lock().await;
+    let queue = handler.queue();
+    let mut queue_str = "## Queue \n".to_string();
+    if queue.is_empty() {
+        queue_str.push_str("Empty, add a track by executing `/play` command :)");
+    } else {
+        let metdatas = TRACK_METADATA.lock().await;
+        for (index, song) in queue.current_queue().into_iter().enumerate() {
+            // Safe to unwrap because we are sure that the metadata exists
+            let metadata = &metdatas.get(&song.uuid()).unwrap().metadata;
+            queue_str.push_str(&format!(
+                "{}. {}{}\n",
+                index + 1,
+                track_link(metadata),
+                if index == 0 { " (Now Playing)" } else { "" }
+            ));
+        }
+    }
+    send_reply(
+        &ctx,
+        info_reply(
+            Some(ctx.serenity_context()),
+            queue_str,
+            Some("Music".to_string()),
+        )
+        .await,
+    )
+    .await;
+    Ok(())
+}
+
+/// Skips the current track
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn skip(ctx: Context<'_>) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let handler = handler_lock.lock().await;
+    let song = notify_if_empty_queue(&ctx, &handler).await;
+    if song.is_none() {
+        return Ok(());
+    }
+    match skip_current(handler.queue()).await {
+        Ok(_) => {
+            send_reply(
+                &ctx,
+                info_reply(
+                    Some(ctx.serenity_context()),
+                    "Skipped the current track.".to_string(),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+        Err(why) => {
+            error!("Failed to skip track: {:?}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to skip track: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    };
+    Ok(())
+}
+
+/// Stops the music player and disconnect the voice channel
+#[poise::command(slash_command, prefix_command, guild_only, aliases("leave"))]
+pub async fn stop(ctx: Context<'_>) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let mut handler = handler_lock.lock().await;
+    handler.queue().stop();
+    handler.remove_all_global_events();
+    handler.leave().await.unwrap();
+    if let Err(why) = ctx
+        .send(
+            info_reply(
+                Some(ctx.serenity_context()),
+                "Stopped the music player and left the voice channel.".to_string(),
+                Some("Music".to_string()),
+            )
+            .await,
+        )
+        .await
+    {
+        error!("Failed to send message: {:?}", why);
+    }
+    Ok(())
+}
+
+/// Unloops the current track
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn unloop(
+    ctx: Context<'_>,
+) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let handler = handler_lock.lock().await;
+    let song = notify_if_empty_queue(&ctx, &handler).await;
+    if song.is_none() {
+        return Ok(());
+    }
+    let song = song.unwrap();
+    match song.disable_loop() {
+        Ok(_) => {
+            send_reply(
+                &ctx,
+                info_reply(
+                    Some(ctx.serenity_context()),
+                    "Unlooped the current track.".to_string(),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+        Err(why) => {
+            error!("Failed to unloop track: {:?}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to unloop track: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+/// Sets the volume of the current player
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn volume(
+    ctx: Context<'_>,
+    #[description = "The volume to set (0-100)"] volume: i8,
+) -> Result<(), Error> {
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if notify_if_not_vc(&ctx, &manager).await {
+        return Ok(());
+    }
+    let handler_lock = manager.get(ctx.guild_id().unwrap()).unwrap();
+    let handler = handler_lock.lock().await;
+    if notify_if_empty_queue(&ctx, &handler).await.is_none() {
+        return Ok(());
+    }
+    match set_volume(&handler, volume).await {
+        Ok(_) => {
+            send_reply(
+                &ctx,
+                info_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Set volume to {}%.", volume),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+        Err(why) => {
+            error!("Failed to set volume: {:?}", why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to set volume: {}", why),
+                    Some("Music".to_string()),
+                )
+                .await,
+            )
+            .await;
+        }
+    }
+    Ok(())
+}
+
+pub fn exports() -> Vec<
+    poise::Command<
+        crate::commands::Data,
+        Box<dyn serde::ser::StdError + std::marker::Send + Sync + 'static>,
+    >,
+> {
+    vec![
+        announcements::announcements(),
+        cookies::cookies(),
+        history::history(),
+        join(),
+        _loop(),
+        lyrics::lyrics(),
+        play_command(),
+        playlist::playlist(),
+        history::previous(),
+        podcast::podcast(),
+        pause(),
+        player::player(),
+        resume(),
+        queue(),
+        radio::radio(),
+        history::replay(),
+        requests::request_channel(),
+        skip(),
+        stats::stats(),
+        stop(),
+        unloop(),
+        volume(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn play_is_one_command_for_both_forms() {
+        let commands = exports();
+        let play: Vec<_> = commands
+            .iter()
+            .filter(|command| command.name == "play")
+            .collect();
+        assert_eq!(play.len(), 1);
+        assert!(play[0].prefix_action.is_some());
+        assert!(play[0].slash_action.is_some());
+        let parameters: Vec<&str> = play[0]
+            .parameters
+            .iter()
+            .map(|parameter| parameter.name.as_str())
+            .collect();
+        assert_eq!(parameters, ["query", "file", "source"]);
+    }
+}
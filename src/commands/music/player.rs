@@ -0,0 +1,417 @@
+use super::settings;
+use super::{
+    TRACK_METADATA, VOICE_CHAT_PROPERTIES, check_in_vc, set_volume, skip_current, track_link,
+};
+use crate::commands::{Context, Error};
+use crate::utils::message::{error_embed, info_embed, info_reply};
+use crate::utils::time::format_duration;
+use rand::seq::SliceRandom;
+use serenity::all::{
+    ButtonStyle, ChannelId, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage,
+    GuildId, Http, Message, MessageId,
+};
+use serenity::async_trait;
+use serenity::client::Context as SerenityContext;
+use songbird::events::{Event, EventContext, EventHandler as VoiceEventHandler};
+use songbird::tracks::{LoopState, PlayMode};
+use songbird::{Call, Songbird};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock};
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+/// Prefix of the custom ids of the panel buttons.
+pub const BUTTON_PREFIX: &str = "player:";
+const VOLUME_STEP: i8 = 10;
+
+/// The control panel messages of each guild, the one of `/player` and the request channel's.
+static PANELS: LazyLock<Mutex<HashMap<GuildId, Vec<Message>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+enum PanelAction {
+    TogglePause,
+    Skip,
+    Stop,
+    ToggleLoop,
+    Shuffle,
+    VolumeDown,
+    VolumeUp,
+}
+
+impl PanelAction {
+    fn from_id(custom_id: &str) -> Option<PanelAction> {
+        match custom_id.strip_prefix(BUTTON_PREFIX)? {
+            "pause" => Some(PanelAction::TogglePause),
+            "skip" => Some(PanelAction::Skip),
+            "stop" => Some(PanelAction::Stop),
+            "loop" => Some(PanelAction::ToggleLoop),
+            "shuffle" => Some(PanelAction::Shuffle),
+            "volume_down" => Some(PanelAction::VolumeDown),
+            "volume_up" => Some(PanelAction::VolumeUp),
+            _ => None,
+        }
+    }
+}
+
+/// What the panel shows about the player.
+struct PanelState {
+    description: String,
+    playing: bool,
+    paused: bool,
+    looping: bool,
+}
+
+async fn panel_state(manager: &Arc<Songbird>, guild_id: GuildId) -> PanelState {
+    let mut state = PanelState {
+        description: "Nothing is playing, add a track with `/play`.".to_string(),
+        playing: false,
+        paused: false,
+        looping: false,
+    };
+    let Some(handler_lock) = manager.get(guild_id) else {
+        return state;
+    };
+    let handler = handler_lock.lock().await;
+    let Some(song) = handler.queue().current() else {
+        return state;
+    };
+    let Ok(info) = song.get_info().await else {
+        return state;
+    };
+    if info.playing.is_done() {
+        return state;
+    }
+    state.playing = true;
+    state.paused = info.playing == PlayMode::Pause;
+    state.looping = info.loops != LoopState::Finite(0);
+    let volume = match handler.current_channel() {
+        Some(channel) => VOICE_CHAT_PROPERTIES
+            .lock()
+            .await
+            .get(&channel)
+            .map(|properties| properties.volume)
+            .unwrap_or(100),
+        None => 100,
+    };
+    let mut description = String::new();
+    if let Some(track) = TRACK_METADATA.lock().await.get(&song.uuid()) {
+        description.push_str(&format!(
+            "**Now playing:** {}\nRequested by <@{}>\n",
+            track_link(&track.metadata),
+            track.requester
+        ));
+        let position = format_duration(info.position);
+        match track.metadata.duration {
+            Some(duration) => {
+                description.push_str(&format!("`{} / {}`\n", position, format_duration(duration)))
+            }
+            None => description.push_str(&format!("`{}`\n", position)),
+        }
+    }
+    description.push_str(&format!(
+        "\nVolume: {}% · Loop: {} · {} tracks in queue{}",
+        volume,
+        if state.looping { "on" } else { "off" },
+        handler.queue().len(),
+        if state.paused { " · Paused" } else { "" }
+    ));
+    state.description = description;
+    state
+}
+
+fn button(action: &str, label: &str, style: ButtonStyle, enabled: bool) -> CreateButton {
+    CreateButton::new(format!("{}{}", BUTTON_PREFIX, action))
+        .label(label)
+        .style(style)
+        .disabled(!enabled)
+}
+
+fn components(state: &PanelState) -> Vec<CreateActionRow> {
+    vec![
+        CreateActionRow::Buttons(vec![
+            if state.paused {
+                button("pause", "▶ Resume", ButtonStyle::Success, state.playing)
+            } else {
+                button("pause", "⏸ Pause", ButtonStyle::Primary, state.playing)
+            },
+            button("skip", "⏭ Skip", ButtonStyle::Primary, state.playing),
+            button("stop", "⏹ Stop", ButtonStyle::Danger, true),
+        ]),
+        CreateActionRow::Buttons(vec![
+            button(
+                "loop",
+                "🔁 Loop",
+                if state.looping {
+                    ButtonStyle::Success
+                } else {
+                    ButtonStyle::Secondary
+                },
+                state.playing,
+            ),
+            button(
+                "shuffle",
+                "🔀 Shuffle",
+                ButtonStyle::Secondary,
+                state.playing,
+            ),
+            button(
+                "volume_down",
+                "🔉 -10%",
+                ButtonStyle::Secondary,
+                state.playing,
+            ),
+            button(
+                "volume_up",
+                "🔊 +10%",
+                ButtonStyle::Secondary,
+                state.playing,
+            ),
+        ]),
+    ]
+}
+
+/// Updates the panels of a guild in place, keeping the rest of their embed.
+pub async fn refresh(http: &Http, manager: &Arc<Songbird>, guild_id: GuildId) {
+    let Some(messages) = PANELS.lock().await.get(&guild_id).cloned() else {
+        return;
+    };
+    let state = panel_state(manager, guild_id).await;
+    for mut message in messages {
+        let Some(embed) = message.embeds.first().cloned() else {
+            continue;
+        };
+        let edit = EditMessage::new()
+            .embed(CreateEmbed::from(embed).description(state.description.clone()))
+            .components(components(&state));
+        if let Err(why) = message.edit(http, edit).await {
+            // The panel was most likely deleted, stop updating it.
+            debug!("Failed to update the player panel: {:?}", why);
+            if let Some(panels) = PANELS.lock().await.get_mut(&guild_id) {
+                panels.retain(|panel| panel.id != message.id);
+            }
+        }
+    }
+}
+
+/// Keeps a panel up to date from now on.
+async fn register(guild_id: GuildId, message: Message) {
+    PANELS
+        .lock()
+        .await
+        .entry(guild_id)
+        .or_default()
+        .push(message);
+}
+
+/// Sends a new panel to a channel.
+pub async fn send_panel(
+    ctx: &SerenityContext,
+    manager: &Arc<Songbird>,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> serenity::Result<Message> {
+    let state = panel_state(manager, guild_id).await;
+    let message = channel_id
+        .send_message(
+            ctx,
+            CreateMessage::new()
+                .embed(
+                    info_embed(
+                        Some(ctx),
+                        Some("Player".to_string()),
+                        Some(state.description.clone()),
+                    )
+                    .await,
+                )
+                .components(components(&state)),
+        )
+        .await?;
+    register(guild_id, message.clone()).await;
+    Ok(message)
+}
+
+/// Keeps updating a panel sent before a restart, if it isn't already.
+pub async fn restore_panel(
+    http: &Http,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+) -> serenity::Result<()> {
+    let registered = PANELS
+        .lock()
+        .await
+        .get(&guild_id)
+        .is_some_and(|panels| panels.iter().any(|panel| panel.id == message_id));
+    if !registered {
+        register(guild_id, channel_id.message(http, message_id).await?).await;
+    }
+    Ok(())
+}
+
+/// Stops updating a panel.
+pub async fn forget_panel(guild_id: GuildId, message_id: MessageId) {
+    if let Some(panels) = PANELS.lock().await.get_mut(&guild_id) {
+        panels.retain(|panel| panel.id != message_id);
+    }
+}
+
+/// Refreshes the panel when tracks start, pause or end.
+pub struct PanelUpdater {
+    pub guild_id: GuildId,
+    pub http: Arc<Http>,
+    pub songbird: Arc<Songbird>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for PanelUpdater {
+    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
+        refresh(&self.http, &self.songbird, self.guild_id).await;
+        None
+    }
+}
+
+async fn apply(action: &PanelAction, handler: &mut Call) -> Result<(), String> {
+    let queue = handler.queue().clone();
+    let current = queue.current();
+    match action {
+        PanelAction::TogglePause => {
+            let Some(song) = current else {
+                return Ok(());
+            };
+            let info = song.get_info().await.map_err(|why| why.to_string())?;
+            if info.playing == PlayMode::Play {
+                song.pause()
+            } else {
+                song.play()
+            }
+            .map_err(|why| why.to_string())
+        }
+        PanelAction::Skip => skip_current(&queue).await.map_err(|why| why.to_string()),
+        PanelAction::Stop => {
+            queue.stop();
+            handler.remove_all_global_events();
+            handler.leave().await.map_err(|why| why.to_string())
+        }
+        PanelAction::ToggleLoop => {
+            let Some(song) = current else {
+                return Ok(());
+            };
+            let info = song.get_info().await.map_err(|why| why.to_string())?;
+            if info.loops == LoopState::Finite(0) {
+                song.enable_loop()
+            } else {
+                song.disable_loop()
+            }
+            .map_err(|why| why.to_string())
+        }
+        PanelAction::Shuffle => {
+            queue.modify_queue(|tracks| {
+                // Keep the current track where it is.
+                let tracks = tracks.make_contiguous();
+                if tracks.len() > 2 {
+                    tracks[1..].shuffle(&mut rand::rng());
+                }
+            });
+            Ok(())
+        }
+        PanelAction::VolumeDown | PanelAction::VolumeUp => {
+            let volume = match handler.current_channel() {
+                Some(channel) => VOICE_CHAT_PROPERTIES
+                    .lock()
+                    .await
+                    .get(&channel)
+                    .map(|properties| properties.volume)
+                    .unwrap_or(100),
+                None => 100,
+            };
+            let volume = if matches!(action, PanelAction::VolumeUp) {
+                volume.saturating_add(VOLUME_STEP).min(100)
+            } else {
+                volume.saturating_sub(VOLUME_STEP).max(0)
+            };
+            set_volume(handler, volume)
+                .await
+                .map_err(|why| why.to_string())
+        }
+    }
+}
+
+/// Handles a press on one of the panel buttons.
+pub async fn handle_button(ctx: &SerenityContext, interaction: &ComponentInteraction) {
+    let (Some(action), Some(guild_id)) = (
+        PanelAction::from_id(&interaction.data.custom_id),
+        interaction.guild_id,
+    ) else {
+        return;
+    };
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let result = match check_in_vc(&ctx.cache, &manager, guild_id, interaction.user.id).await {
+        Ok(()) => {
+            let handler_lock = manager.get(guild_id).unwrap();
+            let mut handler = handler_lock.lock().await;
+            apply(&action, &mut handler).await
+        }
+        Err(why) => Err(why.to_string()),
+    };
+    let response = match result {
+        Ok(()) => {
+            let state = panel_state(&manager, guild_id).await;
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(
+                        info_embed(
+                            Some(ctx),
+                            Some("Player".to_string()),
+                            Some(state.description.clone()),
+                        )
+                        .await,
+                    )
+                    .components(components(&state)),
+            )
+        }
+        Err(why) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(error_embed(Some(ctx), Some("Player".to_string()), Some(why)).await)
+                .ephemeral(true),
+        ),
+    };
+    if let Err(why) = interaction.create_response(ctx, response).await {
+        error!("Failed to respond to a player button: {:?}", why);
+    }
+}
+
+/// Shows a control panel for the player that stays up to date
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn player(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    let state = panel_state(&manager, guild_id).await;
+    let reply = info_reply(
+        Some(ctx.serenity_context()),
+        state.description.clone(),
+        Some("Player".to_string()),
+    )
+    .await
+    .components(components(&state));
+    let message = ctx.send(reply).await?.into_message().await?;
+    // Only keep one panel besides the request channel's, the old one would go stale.
+    let request_panel = settings::get(guild_id).await.request_panel;
+    let old_panels: Vec<Message> = match PANELS.lock().await.get_mut(&guild_id) {
+        Some(panels) => {
+            let (old, kept) = panels
+                .drain(..)
+                .partition(|panel| Some(panel.id.get()) != request_panel);
+            *panels = kept;
+            old
+        }
+        None => vec![],
+    };
+    for old in old_panels {
+        if let Err(why) = old.delete(ctx).await {
+            debug!("Failed to delete the old player panel: {:?}", why);
+        }
+    }
+    register(guild_id, message).await;
+    Ok(())
+}
@@ -0,0 +1,857 @@
+use super::{QueuedTrack, TRACK_METADATA, enqueue_queries, join_if_not_in_vc, query_track, track_link};
+use crate::commands::{Context, Error};
+use crate::storage;
+use crate::utils::message::{error_reply, info_reply, send_reply};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use serenity::all::{Attachment, CreateAttachment};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::LazyLock;
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+const PLAYLISTS_FILE: &str = "playlists";
+const MAX_NAME_LENGTH: usize = 32;
+const MAX_TRACKS: usize = 200;
+pub const MAX_IMPORT_SIZE: u32 = 256 * 1024;
+/// Attachments `/play` treats as playlists rather than ignoring.
+const PLAYLIST_EXTENSIONS: [&str; 4] = ["m3u", "m3u8", "pls", "txt"];
+
+/// Playlists by owner (`user:<id>` or `guild:<id>`) and lowercased name.
+static PLAYLISTS: LazyLock<Mutex<HashMap<String, BTreeMap<String, Playlist>>>> =
+    LazyLock::new(|| Mutex::new(storage::load(PLAYLISTS_FILE)));
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PlaylistTrack {
+    pub title: String,
+    pub query: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Playlist {
+    pub name: String,
+    /// User who created the playlist, allowed to edit it even when it belongs to a server.
+    pub creator: u64,
+    pub tracks: Vec<PlaylistTrack>,
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq)]
+pub enum Scope {
+    #[name = "personal"]
+    Personal,
+    #[name = "server"]
+    Server,
+}
+
+#[derive(poise::ChoiceParameter, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    #[name = "text"]
+    Text,
+    #[name = "m3u"]
+    M3u,
+    #[name = "json"]
+    Json,
+}
+
+fn owner_key(ctx: &Context<'_>, scope: Scope) -> String {
+    match scope {
+        Scope::Personal => format!("user:{}", ctx.author().id),
+        Scope::Server => format!("guild:{}", ctx.guild_id().unwrap()),
+    }
+}
+
+/// Whether the author can manage the server, checked before locking the playlists since
+/// fetching the member can take a request to Discord.
+async fn can_manage_guild(ctx: &Context<'_>, scope: Scope) -> bool {
+    if scope == Scope::Personal {
+        return false;
+    }
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+    ctx.guild().is_some_and(|guild| {
+        guild
+            .channels
+            .get(&ctx.channel_id())
+            .is_some_and(|channel| guild.user_permissions_in(channel, &member).manage_guild())
+    })
+}
+
+/// Server playlists can be edited by their creator and members who can manage the server.
+fn can_edit(ctx: &Context<'_>, scope: Scope, playlist: &Playlist, manages_guild: bool) -> bool {
+    scope == Scope::Personal || playlist.creator == ctx.author().id.get() || manages_guild
+}
+
+fn save(playlists: &HashMap<String, BTreeMap<String, Playlist>>) {
+    if let Err(why) = storage::save(PLAYLISTS_FILE, playlists) {
+        error!("Failed to save playlists: {:?}", why);
+    }
+}
+
+/// Whether an attachment looks like a playlist file `/play` can queue.
+pub fn is_playlist_file(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| PLAYLIST_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Reads playlist entries from a plain text (one query per line), M3U, PLS or exported JSON file.
+pub fn parse_file(file_name: &str, content: &str) -> Result<Vec<PlaylistTrack>, String> {
+    let file_name = file_name.to_lowercase();
+    if file_name.ends_with(".json") {
+        let playlist: Playlist = serde_json::from_str(content)
+            .map_err(|why| format!("invalid playlist JSON: {}", why))?;
+        return Ok(playlist.tracks);
+    }
+    if file_name.ends_with(".pls") || content.trim_start().starts_with("[playlist]") {
+        return Ok(parse_pls(content));
+    }
+    let mut tracks = vec![];
+    let mut title = None;
+    for line in content.lines() {
+        let line = line.trim();
+        // Titles of M3U entries come from the `#EXTINF:<duration>,<title>` line before them.
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            title = info
+                .split_once(',')
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty());
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        tracks.push(PlaylistTrack {
+            title: title.take().unwrap_or_else(|| line.to_string()),
+            query: line.to_string(),
+            source: None,
+        });
+    }
+    Ok(tracks)
+}
+
+/// Reads the numbered `FileN=` and `TitleN=` entries of a PLS file.
+fn parse_pls(content: &str) -> Vec<PlaylistTrack> {
+    let mut entries: BTreeMap<usize, (Option<String>, Option<String>)> = BTreeMap::new();
+    for line in content.lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let value = value.trim().to_string();
+        let key = key.trim().to_lowercase();
+        if let Some(index) = key.strip_prefix("file").and_then(|n| n.parse().ok()) {
+            entries.entry(index).or_default().0 = Some(value);
+        } else if let Some(index) = key.strip_prefix("title").and_then(|n| n.parse().ok()) {
+            entries.entry(index).or_default().1 = Some(value).filter(|title| !title.is_empty());
+        }
+    }
+    entries
+        .into_values()
+        .filter_map(|(file, title)| {
+            let query = file.filter(|file| !file.is_empty())?;
+            Some(PlaylistTrack {
+                title: title.unwrap_or_else(|| query.clone()),
+                query,
+                source: None,
+            })
+        })
+        .collect()
+}
+
+fn export_file(playlist: &Playlist, format: ExportFormat) -> (String, String) {
+    let slug: String = playlist
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    match format {
+        ExportFormat::Text => (
+            format!("{}.txt", slug),
+            playlist
+                .tracks
+                .iter()
+                .map(|track| format!("{}\n", track.query))
+                .collect(),
+        ),
+        ExportFormat::M3u => {
+            let mut content = "#EXTM3U\n".to_string();
+            for track in &playlist.tracks {
+                content.push_str(&format!("#EXTINF:-1,{}\n{}\n", track.title, track.query));
+            }
+            (format!("{}.m3u", slug), content)
+        }
+        ExportFormat::Json => (
+            format!("{}.json", slug),
+            serde_json::to_string_pretty(playlist).unwrap(),
+        ),
+    }
+}
+
+async fn reply(ctx: &Context<'_>, content: String, is_error: bool) {
+    let reply = if is_error {
+        error_reply(
+            Some(ctx.serenity_context()),
+            content,
+            Some("Playlist".to_string()),
+        )
+        .await
+    } else {
+        info_reply(
+            Some(ctx.serenity_context()),
+            content,
+            Some("Playlist".to_string()),
+        )
+        .await
+    };
+    send_reply(ctx, reply).await;
+}
+
+async fn not_found(ctx: &Context<'_>, name: &str) {
+    reply(
+        ctx,
+        format!("No playlist named `{}` was found.", name),
+        true,
+    )
+    .await;
+}
+
+async fn autocomplete_playlist<'a>(
+    ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let partial = partial.to_lowercase();
+    let playlists = PLAYLISTS.lock().await;
+    let mut names = vec![];
+    for scope in [Scope::Personal, Scope::Server] {
+        if let Some(owned) = playlists.get(&owner_key(&ctx, scope)) {
+            names.extend(
+                owned
+                    .iter()
+                    .filter(|(key, _)| key.contains(&partial))
+                    .map(|(_, playlist)| playlist.name.clone()),
+            );
+        }
+    }
+    names.sort();
+    names.dedup();
+    names.into_iter()
+}
+
+/// Manages saved playlists
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands(
+        "create", "add", "remove", "show", "play", "delete", "import", "export"
+    ),
+    subcommand_required
+)]
+pub async fn playlist(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Creates an empty playlist, or one with the tracks of the current queue
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn create(
+    ctx: Context<'_>,
+    #[description = "The name of the playlist"] name: String,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+    #[description = "Save the tracks of the current queue"] from_queue: Option<bool>,
+) -> Result<(), Error> {
+    let scope = scope.unwrap_or(Scope::Personal);
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        reply(
+            &ctx,
+            format!(
+                "Playlist names must be between 1 and {} characters long.",
+                MAX_NAME_LENGTH
+            ),
+            true,
+        )
+        .await;
+        return Ok(());
+    }
+    let mut tracks = vec![];
+    if from_queue.unwrap_or(false) {
+        let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+        if let Some(handler_lock) = manager.get(ctx.guild_id().unwrap()) {
+            let queue = handler_lock.lock().await.queue().current_queue();
+            let metadatas = TRACK_METADATA.lock().await;
+            tracks = queue
+                .iter()
+                .filter_map(|song| metadatas.get(&song.uuid()))
+                .filter_map(|QueuedTrack { metadata, .. }| {
+                    let query = metadata.source_url.clone()?;
+                    Some(PlaylistTrack {
+                        title: metadata.title.clone().unwrap_or_else(|| query.clone()),
+                        query,
+                        source: None,
+                    })
+                })
+                .take(MAX_TRACKS)
+                .collect();
+        }
+    }
+    let count = tracks.len();
+    {
+        let mut playlists = PLAYLISTS.lock().await;
+        let owned = playlists.entry(owner_key(&ctx, scope)).or_default();
+        if owned.contains_key(&name.to_lowercase()) {
+            drop(playlists);
+            reply(
+                &ctx,
+                format!("A playlist named `{}` already exists.", name),
+                true,
+            )
+            .await;
+            return Ok(());
+        }
+        owned.insert(
+            name.to_lowercase(),
+            Playlist {
+                name: name.clone(),
+                creator: ctx.author().id.get(),
+                tracks,
+            },
+        );
+        save(&playlists);
+    }
+    reply(
+        &ctx,
+        format!("Created playlist `{}` with {} tracks.", name, count),
+        false,
+    )
+    .await;
+    Ok(())
+}
+
+/// Adds a track to a playlist
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn add(
+    ctx: Context<'_>,
+    #[description = "The name of the playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    name: String,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+    #[description = "The track to add, can be url or query"]
+    #[rest]
+    query: String,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let scope = scope.unwrap_or(Scope::Personal);
+    // Resolve first so the playlist only gets tracks that can be played.
+    let track = match query_track(query.clone(), None).await {
+        Ok(track) => track,
+        Err(why) => {
+            debug!("Failed to resolve track for playlist: {}", why);
+            reply(&ctx, why.user_message(), true).await;
+            return Ok(());
+        }
+    };
+    let entry = PlaylistTrack {
+        title: track
+            .metadata
+            .title
+            .clone()
+            .unwrap_or_else(|| query.clone()),
+        query: track.metadata.source_url.clone().unwrap_or(query),
+        source: None,
+    };
+    let manages_guild = can_manage_guild(&ctx, scope).await;
+    let mut playlists = PLAYLISTS.lock().await;
+    let Some(playlist) = playlists
+        .get_mut(&owner_key(&ctx, scope))
+        .and_then(|owned| owned.get_mut(&name.to_lowercase()))
+    else {
+        drop(playlists);
+        not_found(&ctx, &name).await;
+        return Ok(());
+    };
+    if !can_edit(&ctx, scope, playlist, manages_guild) {
+        drop(playlists);
+        reply(&ctx, "You can't edit this playlist.".to_string(), true).await;
+        return Ok(());
+    }
+    if playlist.tracks.len() >= MAX_TRACKS {
+        drop(playlists);
+        reply(
+            &ctx,
+            format!("Playlists can't have more than {} tracks.", MAX_TRACKS),
+            true,
+        )
+        .await;
+        return Ok(());
+    }
+    let message = format!(
+        "Added {} to `{}`.",
+        track_link(&track.metadata),
+        playlist.name
+    );
+    playlist.tracks.push(entry);
+    save(&playlists);
+    drop(playlists);
+    reply(&ctx, message, false).await;
+    Ok(())
+}
+
+/// Removes a track from a playlist
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn remove(
+    ctx: Context<'_>,
+    #[description = "The name of the playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    name: String,
+    #[description = "The position of the track in the playlist"]
+    #[min = 1]
+    position: usize,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+) -> Result<(), Error> {
+    let scope = scope.unwrap_or(Scope::Personal);
+    let manages_guild = can_manage_guild(&ctx, scope).await;
+    let mut playlists = PLAYLISTS.lock().await;
+    let Some(playlist) = playlists
+        .get_mut(&owner_key(&ctx, scope))
+        .and_then(|owned| owned.get_mut(&name.to_lowercase()))
+    else {
+        drop(playlists);
+        not_found(&ctx, &name).await;
+        return Ok(());
+    };
+    if !can_edit(&ctx, scope, playlist, manages_guild) {
+        drop(playlists);
+        reply(&ctx, "You can't edit this playlist.".to_string(), true).await;
+        return Ok(());
+    }
+    if position == 0 || position > playlist.tracks.len() {
+        drop(playlists);
+        reply(
+            &ctx,
+            format!("There's no track {} in `{}`.", position, name),
+            true,
+        )
+        .await;
+        return Ok(());
+    }
+    let track = playlist.tracks.remove(position - 1);
+    let message = format!("Removed {} from `{}`.", track.title, playlist.name);
+    save(&playlists);
+    drop(playlists);
+    reply(&ctx, message, false).await;
+    Ok(())
+}
+
+/// Lists your playlists and the server's, or the tracks of one
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn show(
+    ctx: Context<'_>,
+    #[description = "The name of the playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    name: Option<String>,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+) -> Result<(), Error> {
+    let playlists = PLAYLISTS.lock().await;
+    let Some(name) = name else {
+        let mut list = String::new();
+        for (scope, title) in [
+            (Scope::Personal, "Your playlists"),
+            (Scope::Server, "Server playlists"),
+        ] {
+            list.push_str(&format!("## {}\n", title));
+            match playlists.get(&owner_key(&ctx, scope)) {
+                Some(owned) if !owned.is_empty() => {
+                    for playlist in owned.values() {
+                        list.push_str(&format!(
+                            "- `{}` ({} tracks)\n",
+                            playlist.name,
+                            playlist.tracks.len()
+                        ));
+                    }
+                }
+                _ => list.push_str("None yet, create one with `/playlist create`.\n"),
+            }
+        }
+        drop(playlists);
+        reply(&ctx, list, false).await;
+        return Ok(());
+    };
+    let scope = scope.unwrap_or(Scope::Personal);
+    let Some(playlist) = playlists
+        .get(&owner_key(&ctx, scope))
+        .and_then(|owned| owned.get(&name.to_lowercase()))
+    else {
+        drop(playlists);
+        not_found(&ctx, &name).await;
+        return Ok(());
+    };
+    let mut list = format!("## {}\n", playlist.name);
+    if playlist.tracks.is_empty() {
+        list.push_str("Empty, add a track with `/playlist add`.");
+    }
+    for (index, track) in playlist.tracks.iter().enumerate() {
+        let line = if track.query.starts_with("http") {
+            format!("{}. [{}]({})\n", index + 1, track.title, track.query)
+        } else {
+            format!("{}. {}\n", index + 1, track.title)
+        };
+        // Stay within the embed description limit.
+        if list.len() + line.len() > 4000 {
+            list.push_str(&format!("...and {} more\n", playlist.tracks.len() - index));
+            break;
+        }
+        list.push_str(&line);
+    }
+    drop(playlists);
+    reply(&ctx, list, false).await;
+    Ok(())
+}
+
+/// Adds all tracks of a playlist to the queue
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn play(
+    ctx: Context<'_>,
+    #[description = "The name of the playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    name: String,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let scope = scope.unwrap_or(Scope::Personal);
+    let playlist = PLAYLISTS
+        .lock()
+        .await
+        .get(&owner_key(&ctx, scope))
+        .and_then(|owned| owned.get(&name.to_lowercase()))
+        .cloned();
+    let Some(playlist) = playlist else {
+        not_found(&ctx, &name).await;
+        return Ok(());
+    };
+    if playlist.tracks.is_empty() {
+        reply(
+            &ctx,
+            format!("`{}` doesn't have any tracks.", playlist.name),
+            true,
+        )
+        .await;
+        return Ok(());
+    }
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if !join_if_not_in_vc(ctx, &manager).await {
+        return Ok(());
+    }
+    let queries = playlist
+        .tracks
+        .into_iter()
+        .map(|track| (track.query, track.source))
+        .collect();
+    let summary = enqueue_queries(ctx, &manager, queries).await;
+    let message = format!(
+        "Added {} tracks from `{}` to the queue.{}",
+        summary.queued,
+        playlist.name,
+        summary.details()
+    );
+    reply(&ctx, message, summary.queued == 0).await;
+    Ok(())
+}
+
+/// Deletes a playlist
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn delete(
+    ctx: Context<'_>,
+    #[description = "The name of the playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    name: String,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+) -> Result<(), Error> {
+    let scope = scope.unwrap_or(Scope::Personal);
+    let manages_guild = can_manage_guild(&ctx, scope).await;
+    let mut playlists = PLAYLISTS.lock().await;
+    let key = owner_key(&ctx, scope);
+    let Some(playlist) = playlists
+        .get(&key)
+        .and_then(|owned| owned.get(&name.to_lowercase()))
+    else {
+        drop(playlists);
+        not_found(&ctx, &name).await;
+        return Ok(());
+    };
+    if !can_edit(&ctx, scope, playlist, manages_guild) {
+        drop(playlists);
+        reply(&ctx, "You can't delete this playlist.".to_string(), true).await;
+        return Ok(());
+    }
+    let owned = playlists.get_mut(&key).unwrap();
+    let playlist = owned.remove(&name.to_lowercase()).unwrap();
+    if owned.is_empty() {
+        playlists.remove(&key);
+    }
+    save(&playlists);
+    drop(playlists);
+    reply(
+        &ctx,
+        format!("Deleted playlist `{}`.", playlist.name),
+        false,
+    )
+    .await;
+    Ok(())
+}
+
+/// Creates a playlist from a text, M3U or JSON file
+#[poise::command(slash_command, guild_only)]
+pub async fn import(
+    ctx: Context<'_>,
+    #[description = "The name of the new playlist"] name: String,
+    #[description = "A text file with one url or query per line, an M3U or an exported JSON"]
+    file: Attachment,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+) -> Result<(), Error> {
+    let scope = scope.unwrap_or(Scope::Personal);
+    let name = name.trim().to_string();
+    if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+        reply(
+            &ctx,
+            format!(
+                "Playlist names must be between 1 and {} characters long.",
+                MAX_NAME_LENGTH
+            ),
+            true,
+        )
+        .await;
+        return Ok(());
+    }
+    if file.size > MAX_IMPORT_SIZE {
+        reply(&ctx, "The playlist file is too large.".to_string(), true).await;
+        return Ok(());
+    }
+    let tracks = String::from_utf8(file.download().await?)
+        .map_err(|_| "the file isn't UTF-8 text".to_string())
+        .and_then(|content| parse_file(&file.filename, &content));
+    let mut tracks = match tracks {
+        Ok(tracks) if !tracks.is_empty() => tracks,
+        Ok(_) => {
+            reply(
+                &ctx,
+                "The file doesn't contain any tracks.".to_string(),
+                true,
+            )
+            .await;
+            return Ok(());
+        }
+        Err(why) => {
+            reply(&ctx, format!("Failed to read the playlist: {}", why), true).await;
+            return Ok(());
+        }
+    };
+    tracks.truncate(MAX_TRACKS);
+    let count = tracks.len();
+    {
+        let mut playlists = PLAYLISTS.lock().await;
+        let owned = playlists.entry(owner_key(&ctx, scope)).or_default();
+        if owned.contains_key(&name.to_lowercase()) {
+            drop(playlists);
+            reply(
+                &ctx,
+                format!("A playlist named `{}` already exists.", name),
+                true,
+            )
+            .await;
+            return Ok(());
+        }
+        owned.insert(
+            name.to_lowercase(),
+            Playlist {
+                name: name.clone(),
+                creator: ctx.author().id.get(),
+                tracks,
+            },
+        );
+        save(&playlists);
+    }
+    reply(
+        &ctx,
+        format!("Imported {} tracks into `{}`.", count, name),
+        false,
+    )
+    .await;
+    Ok(())
+}
+
+/// Sends a playlist as a text, M3U or JSON file
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn export(
+    ctx: Context<'_>,
+    #[description = "The name of the playlist"]
+    #[autocomplete = "autocomplete_playlist"]
+    name: String,
+    #[description = "The file format, JSON by default"] format: Option<ExportFormat>,
+    #[description = "Whether the playlist is yours or the server's, personal by default"]
+    scope: Option<Scope>,
+) -> Result<(), Error> {
+    let scope = scope.unwrap_or(Scope::Personal);
+    let playlist = PLAYLISTS
+        .lock()
+        .await
+        .get(&owner_key(&ctx, scope))
+        .and_then(|owned| owned.get(&name.to_lowercase()))
+        .cloned();
+    let Some(playlist) = playlist else {
+        not_found(&ctx, &name).await;
+        return Ok(());
+    };
+    let (file_name, content) = export_file(&playlist, format.unwrap_or(ExportFormat::Json));
+    ctx.send(
+        CreateReply::default().attachment(CreateAttachment::bytes(content.into_bytes(), file_name)),
+    )
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(tracks: &[PlaylistTrack]) -> Vec<(&str, &str)> {
+        tracks
+            .iter()
+            .map(|track| (track.title.as_str(), track.query.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parses_m3u_titles() {
+        let tracks = parse_file(
+            "mix.m3u8",
+            "#EXTM3U\n\
+            #EXTINF:215,Artist - First\n\
+            https://example.com/first.mp3\n\
+            #EXTINF:-1,\n\
+            https://example.com/untitled.mp3\n\
+            #EXTINF:-1 tvg-id=\"x\",Radio, with a comma\n\
+            https://radio.example/stream\n",
+        )
+        .unwrap();
+        assert_eq!(
+            entries(&tracks),
+            [
+                ("Artist - First", "https://example.com/first.mp3"),
+                (
+                    "https://example.com/untitled.mp3",
+                    "https://example.com/untitled.mp3"
+                ),
+                ("Radio, with a comma", "https://radio.example/stream"),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_relative_entries_and_skips_comments() {
+        let tracks = parse_file(
+            "Old.M3U",
+            "# Made by hand\n\
+            \n\
+            music/first.mp3\n\
+            #EXTINF:10,Not used\n\
+            # another comment\n\
+            ../second.flac\n\
+            never gonna give you up\n",
+        )
+        .unwrap();
+        // A title only applies to the entry right after it, comments in between don't count.
+        assert_eq!(
+            entries(&tracks),
+            [
+                ("music/first.mp3", "music/first.mp3"),
+                ("Not used", "../second.flac"),
+                ("never gonna give you up", "never gonna give you up"),
+            ]
+        );
+        assert!(
+            parse_file("empty.txt", "#EXTM3U\n# nothing\n")
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn parses_pls_entries_in_order() {
+        let tracks = parse_file(
+            "radio.pls",
+            "[playlist]\n\
+            Title2=Second\n\
+            File2=https://example.com/2.mp3\n\
+            File10=https://example.com/10.mp3\n\
+            File1=https://example.com/1.mp3\n\
+            Title1=First\n\
+            Title3=Orphan title\n\
+            NumberOfEntries=3\n\
+            Version=2\n",
+        )
+        .unwrap();
+        assert_eq!(
+            entries(&tracks),
+            [
+                ("First", "https://example.com/1.mp3"),
+                ("Second", "https://example.com/2.mp3"),
+                ("https://example.com/10.mp3", "https://example.com/10.mp3"),
+            ]
+        );
+        // The header is enough without the extension.
+        let tracks = parse_file("radio.txt", "[playlist]\nFile1=https://example.com/1.mp3\n");
+        assert_eq!(tracks.unwrap().len(), 1);
+    }
+
+    #[test]
+    fn handles_crlf_line_endings() {
+        let m3u = parse_file(
+            "mix.m3u",
+            "#EXTM3U\r\n#EXTINF:1,First\r\nhttps://example.com/1.mp3\r\n\r\nsecond song\r\n",
+        )
+        .unwrap();
+        assert_eq!(
+            entries(&m3u),
+            [
+                ("First", "https://example.com/1.mp3"),
+                ("second song", "second song"),
+            ]
+        );
+        let pls = parse_file(
+            "radio.pls",
+            "[playlist]\r\nFile1=https://example.com/1.mp3\r\nTitle1=First\r\n",
+        )
+        .unwrap();
+        assert_eq!(entries(&pls), [("First", "https://example.com/1.mp3")]);
+    }
+
+    #[test]
+    fn reads_exported_files_back() {
+        let playlist = Playlist {
+            name: "Road trip".to_string(),
+            creator: 1,
+            tracks: parse_file(
+                "mix.m3u",
+                "#EXTINF:1,First\nhttps://example.com/1.mp3\nsong\n",
+            )
+            .unwrap(),
+        };
+        for format in [ExportFormat::Text, ExportFormat::M3u, ExportFormat::Json] {
+            let (name, content) = export_file(&playlist, format);
+            let tracks = parse_file(&name, &content).unwrap();
+            let queries: Vec<&str> = tracks.iter().map(|track| track.query.as_str()).collect();
+            assert_eq!(queries, ["https://example.com/1.mp3", "song"], "{}", name);
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use super::source::ResolvedTrack;
+use super::{enqueue_track, get_http_client, join_if_not_in_vc, notify_if_queue_full};
+use crate::commands::{Context, Error};
+use crate::storage::{self, BackgroundSaver};
+use crate::utils::message::{error_reply, info_reply, send_reply};
+use crate::utils::time::format_duration;
+use chrono::{DateTime, Utc};
+use serenity::all::{
+    ComponentInteractionCollector, ComponentInteractionDataKind, CreateActionRow,
+    CreateInteractionResponse, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+    UserId,
+};
+use serenity::async_trait;
+use songbird::events::{Event, EventContext, EventHandler as VoiceEventHandler, TrackEvent};
+use songbird::input::{AuxMetadata, HttpRequest};
+use songbird::tracks::PlayMode;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::{debug, error};
+
+const POSITIONS_FILE: &str = "podcast_positions";
+const MAX_EPISODES: usize = 10;
+const SELECT_TIMEOUT: Duration = Duration::from_secs(120);
+/// Positions closer than this to the start or the end aren't worth resuming from.
+const RESUME_MARGIN: Duration = Duration::from_secs(30);
+/// How often the position is saved while playing, so a crash or restart loses little of it.
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Last listened position in seconds, by user and episode.
+static POSITIONS: LazyLock<Mutex<HashMap<u64, HashMap<String, u64>>>> =
+    LazyLock::new(|| Mutex::new(storage::load(POSITIONS_FILE)));
+static SAVER: BackgroundSaver = BackgroundSaver::new(POSITIONS_FILE);
+
+struct Episode {
+    id: String,
+    title: String,
+    published: Option<DateTime<Utc>>,
+    url: String,
+    duration: Option<Duration>,
+    thumbnail: Option<String>,
+}
+
+struct Podcast {
+    title: String,
+    episodes: Vec<Episode>,
+}
+
+async fn fetch_podcast(url: &str) -> Result<Podcast, String> {
+    let response = get_http_client()
+        .await
+        .get(url)
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|why| format!("failed to fetch the feed: {}", why))?;
+    let body = response
+        .bytes()
+        .await
+        .map_err(|why| format!("failed to fetch the feed: {}", why))?;
+    parse_feed(&body)
+}
+
+/// Reads the newest episodes of an RSS or Atom feed.
+fn parse_feed(body: &[u8]) -> Result<Podcast, String> {
+    let feed =
+        feed_rs::parser::parse(body).map_err(|why| format!("failed to parse the feed: {}", why))?;
+    let image = feed
+        .logo
+        .as_ref()
+        .or(feed.icon.as_ref())
+        .map(|image| image.uri.clone());
+    let mut episodes: Vec<Episode> = feed
+        .entries
+        .into_iter()
+        .filter_map(|entry| {
+            // The enclosure and the iTunes tags can end up in separate media objects.
+            let media = &entry.media;
+            let contents = || media.iter().flat_map(|media| &media.content);
+            // RSS enclosures end up as media content, Atom ones as links.
+            let url = contents()
+                .find_map(|content| content.url.clone())
+                .map(|url| url.to_string())
+                .or_else(|| {
+                    entry
+                        .links
+                        .iter()
+                        .find(|link| link.rel.as_deref() == Some("enclosure"))
+                        .map(|link| link.href.clone())
+                })?;
+            Some(Episode {
+                id: if entry.id.is_empty() {
+                    url.clone()
+                } else {
+                    entry.id.clone()
+                },
+                title: entry
+                    .title
+                    .map(|title| title.content)
+                    .unwrap_or_else(|| "Untitled episode".to_string()),
+                published: entry.published.or(entry.updated),
+                duration: media
+                    .iter()
+                    .find_map(|media| media.duration)
+                    .or_else(|| contents().find_map(|content| content.duration)),
+                thumbnail: media
+                    .iter()
+                    .find_map(|media| media.thumbnails.first())
+                    .map(|thumbnail| thumbnail.image.uri.clone())
+                    .or_else(|| image.clone()),
+                url,
+            })
+        })
+        .collect();
+    episodes.sort_by_key(|episode| std::cmp::Reverse(episode.published));
+    episodes.truncate(MAX_EPISODES);
+    Ok(Podcast {
+        title: feed
+            .title
+            .map(|title| title.content)
+            .unwrap_or_else(|| "Podcast".to_string()),
+        episodes,
+    })
+}
+
+async fn saved_position(user_id: UserId, episode_id: &str) -> Option<Duration> {
+    POSITIONS
+        .lock()
+        .await
+        .get(&user_id.get())
+        .and_then(|episodes| episodes.get(episode_id))
+        .map(|secs| Duration::from_secs(*secs))
+}
+
+/// Remembers where the requester stopped listening to an episode.
+#[derive(Clone)]
+struct EpisodeProgressRecorder {
+    user_id: UserId,
+    episode_id: String,
+    duration: Option<Duration>,
+}
+
+#[async_trait]
+impl VoiceEventHandler for EpisodeProgressRecorder {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(tracks) = ctx else {
+            return None;
+        };
+        let (state, _) = tracks.first()?;
+        let near_end = self
+            .duration
+            .is_some_and(|duration| state.position + RESUME_MARGIN >= duration);
+        let finished = state.playing == PlayMode::End || near_end;
+        let mut positions = POSITIONS.lock().await;
+        let episodes = positions.entry(self.user_id.get()).or_default();
+        if finished || state.position < RESUME_MARGIN {
+            episodes.remove(&self.episode_id);
+        } else {
+            debug!(
+                "Saving position {:?} of {} for {}",
+                state.position, self.episode_id, self.user_id
+            );
+            episodes.insert(self.episode_id.clone(), state.position.as_secs());
+        }
+        if episodes.is_empty() {
+            positions.remove(&self.user_id.get());
+        }
+        SAVER.save(positions.clone());
+        None
+    }
+}
+
+/// Lists the recent episodes of a podcast feed to choose one to play
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn podcast(
+    ctx: Context<'_>,
+    #[description = "The URL of the podcast's RSS or Atom feed"] feed: String,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+    let podcast = match fetch_podcast(&feed).await {
+        Ok(podcast) if !podcast.episodes.is_empty() => podcast,
+        Ok(_) => {
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    "The feed doesn't have any playable episodes.".to_string(),
+                    Some("Podcast".to_string()),
+                )
+                .await,
+            )
+            .await;
+            return Ok(());
+        }
+        Err(why) => {
+            debug!("Failed to load podcast {}: {}", feed, why);
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("Failed to load the podcast: {}", why),
+                    Some("Podcast".to_string()),
+                )
+                .await,
+            )
+            .await;
+            return Ok(());
+        }
+    };
+    let mut description = format!("## {}\n", podcast.title);
+    let mut options = vec![];
+    for (index, episode) in podcast.episodes.iter().enumerate() {
+        let date = episode
+            .published
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "Unknown date".to_string());
+        let resume = saved_position(ctx.author().id, &episode.id)
+            .await
+            .map(|position| format!(", resume at {}", format_duration(position)))
+            .unwrap_or_default();
+        description.push_str(&format!(
+            "{}. {} ({}{})\n",
+            index + 1,
+            episode.title,
+            date,
+            resume
+        ));
+        options.push(
+            CreateSelectMenuOption::new(
+                episode.title.chars().take(100).collect::<String>(),
+                index.to_string(),
+            )
+            .description(format!("{}{}", date, resume)),
+        );
+    }
+    let custom_id = format!("podcast-{}", ctx.id());
+    let menu = CreateSelectMenu::new(&custom_id, CreateSelectMenuKind::String { options })
+        .placeholder("Choose an episode");
+    let reply = info_reply(
+        Some(ctx.serenity_context()),
+        description,
+        Some("Podcast".to_string()),
+    )
+    .await;
+    let handle = ctx
+        .send(
+            reply
+                .clone()
+                .components(vec![CreateActionRow::SelectMenu(menu)]),
+        )
+        .await?;
+    let interaction = ComponentInteractionCollector::new(ctx.serenity_context())
+        .author_id(ctx.author().id)
+        .channel_id(ctx.channel_id())
+        .custom_ids(vec![custom_id])
+        .timeout(SELECT_TIMEOUT)
+        .await;
+    // Remove the menu so it can't be used again.
+    handle
+        .edit(ctx, reply.components(vec![]))
+        .await
+        .unwrap_or_else(|why| error!("Failed to edit podcast message: {:?}", why));
+    let Some(interaction) = interaction else {
+        return Ok(());
+    };
+    interaction
+        .create_response(ctx, CreateInteractionResponse::Acknowledge)
+        .await?;
+    let index = match &interaction.data.kind {
+        ComponentInteractionDataKind::StringSelect { values } => {
+            values.first().and_then(|value| value.parse::<usize>().ok())
+        }
+        _ => None,
+    };
+    let Some(episode) = index.and_then(|index| podcast.episodes.get(index)) else {
+        return Ok(());
+    };
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    if !join_if_not_in_vc(ctx, &manager).await || notify_if_queue_full(&ctx, &manager).await {
+        return Ok(());
+    }
+    let track = ResolvedTrack {
+        input: HttpRequest::new(get_http_client().await, episode.url.clone()).into(),
+        metadata: AuxMetadata {
+            title: Some(episode.title.clone()),
+            artist: Some(podcast.title.clone()),
+            channel: Some(podcast.title.clone()),
+            date: episode.published.map(|date| date.to_rfc3339()),
+            duration: episode.duration,
+            thumbnail: episode.thumbnail.clone(),
+            source_url: Some(episode.url.clone()),
+            ..Default::default()
+        },
+        live: false,
+    };
+    let song = enqueue_track(ctx, &manager, track).await;
+    let recorder = EpisodeProgressRecorder {
+        user_id: ctx.author().id,
+        episode_id: episode.id.clone(),
+        duration: episode.duration,
+    };
+    let _ = song.add_event(Event::Periodic(SAVE_INTERVAL, None), recorder.clone());
+    let _ = song.add_event(Event::Track(TrackEvent::End), recorder);
+    if let Some(position) = saved_position(ctx.author().id, &episode.id).await {
+        debug!("Resuming {} at {:?}", episode.id, position);
+        // The seek completes once the track is ready, which may be after the current one ends.
+        let _ = song.seek(position);
+        send_reply(
+            &ctx,
+            info_reply(
+                Some(ctx.serenity_context()),
+                format!("Resuming at {}.", format_duration(position)),
+                Some("Podcast".to_string()),
+            )
+            .await,
+        )
+        .await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+  <channel>
+    <title>Test Cast</title>
+    <image><url>https://example.com/cover.jpg</url></image>
+    <item>
+      <title>Older episode</title>
+      <guid>episode-1</guid>
+      <pubDate>Mon, 01 Jan 2024 10:00:00 GMT</pubDate>
+      <enclosure url="https://example.com/1.mp3" length="1000" type="audio/mpeg"/>
+      <itunes:summary>The first episode.</itunes:summary>
+      <itunes:duration>00:30:00</itunes:duration>
+    </item>
+    <item>
+      <title>Show notes only</title>
+      <guid>notes</guid>
+      <pubDate>Wed, 03 Jan 2024 10:00:00 GMT</pubDate>
+    </item>
+    <item>
+      <title>Newer episode</title>
+      <guid>episode-2</guid>
+      <pubDate>Tue, 02 Jan 2024 10:00:00 GMT</pubDate>
+      <enclosure url="https://example.com/2.mp3" length="1000" type="audio/mpeg"/>
+    </item>
+  </channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>Atom Cast</title>
+  <id>urn:atom-cast</id>
+  <updated>2024-01-02T10:00:00Z</updated>
+  <entry>
+    <title>Atom episode</title>
+    <id>urn:atom-cast:1</id>
+    <updated>2024-01-02T10:00:00Z</updated>
+    <link rel="alternate" href="https://example.com/1"/>
+    <link rel="enclosure" type="audio/ogg" href="https://example.com/1.ogg"/>
+  </entry>
+  <entry>
+    <title>No audio</title>
+    <id>urn:atom-cast:2</id>
+    <updated>2024-01-03T10:00:00Z</updated>
+    <link rel="alternate" href="https://example.com/2"/>
+  </entry>
+</feed>"#;
+
+    #[test]
+    fn parses_rss_feeds() {
+        let podcast = parse_feed(RSS.as_bytes()).unwrap();
+        assert_eq!(podcast.title, "Test Cast");
+        // Newest first, without the entries that have nothing to play.
+        let titles: Vec<&str> = podcast
+            .episodes
+            .iter()
+            .map(|episode| episode.title.as_str())
+            .collect();
+        assert_eq!(titles, ["Newer episode", "Older episode"]);
+        let older = &podcast.episodes[1];
+        assert_eq!(older.id, "episode-1");
+        assert_eq!(older.url, "https://example.com/1.mp3");
+        assert_eq!(older.duration, Some(Duration::from_secs(30 * 60)));
+        assert_eq!(
+            older.published.unwrap().to_rfc3339(),
+            "2024-01-01T10:00:00+00:00"
+        );
+        // The channel image stands in for the missing episode thumbnails.
+        assert_eq!(
+            older.thumbnail.as_deref(),
+            Some("https://example.com/cover.jpg")
+        );
+    }
+
+    #[test]
+    fn parses_atom_feeds() {
+        let podcast = parse_feed(ATOM.as_bytes()).unwrap();
+        assert_eq!(podcast.title, "Atom Cast");
+        assert_eq!(podcast.episodes.len(), 1);
+        let episode = &podcast.episodes[0];
+        assert_eq!(episode.title, "Atom episode");
+        assert_eq!(episode.id, "urn:atom-cast:1");
+        assert_eq!(episode.url, "https://example.com/1.ogg");
+        assert_eq!(episode.duration, None);
+    }
+
+    #[test]
+    fn keeps_the_newest_episodes() {
+        let items: String = (1..=MAX_EPISODES + 5)
+            .map(|day| {
+                format!(
+                    "<item><title>Episode {0}</title><guid>{0}</guid>\
+                    <pubDate>{0:02} Jan 2024 10:00:00 GMT</pubDate>\
+                    <enclosure url=\"https://example.com/{0}.mp3\" type=\"audio/mpeg\"/></item>",
+                    day
+                )
+            })
+            .collect();
+        let rss = format!(
+            "<rss version=\"2.0\"><channel><title>Daily</title>{}</channel></rss>",
+            items
+        );
+        let podcast = parse_feed(rss.as_bytes()).unwrap();
+        assert_eq!(podcast.episodes.len(), MAX_EPISODES);
+        assert_eq!(
+            podcast.episodes[0].title,
+            format!("Episode {}", MAX_EPISODES + 5)
+        );
+        assert!(parse_feed(b"not a feed").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+use super::get_http_client;
+use super::ytdl::{expand, template_vars};
+use crate::config::Config;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::Notify;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+static HEALTHY: AtomicBool = AtomicBool::new(false);
+/// Tells the supervisor to stop the server, there's only one so `notify_one` keeps the permit.
+static SHUTDOWN: Notify = Notify::const_new();
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Checks whether the PO token server answers, logging when its state changes.
+pub async fn check(port: u16) -> bool {
+    let healthy = get_http_client()
+        .await
+        .get(format!("http://127.0.0.1:{}/ping", port))
+        .timeout(HEALTH_CHECK_TIMEOUT)
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success());
+    if HEALTHY.swap(healthy, Ordering::Relaxed) != healthy {
+        if healthy {
+            info!("PO token server is up on port {}", port);
+        } else {
+            warn!(
+                "PO token server on port {} is down, yt-dlp will run without it",
+                port
+            );
+        }
+    }
+    healthy
+}
+
+/// Last known state of the PO token server.
+pub fn is_healthy() -> bool {
+    HEALTHY.load(Ordering::Relaxed)
+}
+
+/// Starts the background tasks that keep the PO token server running and monitored.
+pub fn init(config: &Config) -> Result<(), String> {
+    let workarounds = &config.features.music_player.workarounds;
+    let pot_server = config.features.music_player.pot_server.clone();
+    let port = workarounds.ytdl_pot_server_port;
+    if pot_server.managed {
+        let vars = template_vars(config);
+        let command = pot_server
+            .command
+            .iter()
+            .map(|arg| expand(arg, &vars))
+            .collect::<Result<Vec<String>, String>>()?;
+        if command.is_empty() {
+            return Err("PO token server command is empty".to_string());
+        }
+        let restart_delay = Duration::from_secs(pot_server.restart_delay_secs);
+        tokio::spawn(supervise(command, restart_delay));
+    }
+    let interval = Duration::from_secs(pot_server.health_check_interval_secs.max(1));
+    tokio::spawn(async move {
+        loop {
+            check(port).await;
+            sleep(interval).await;
+        }
+    });
+    Ok(())
+}
+
+async fn supervise(command: Vec<String>, restart_delay: Duration) {
+    loop {
+        info!("Starting PO token server: {}", command.join(" "));
+        let child = Command::new(&command[0])
+            .args(&command[1..])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn();
+        match child {
+            Ok(mut child) => {
+                if let Some(stdout) = child.stdout.take() {
+                    tokio::spawn(forward_output(stdout, false));
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    tokio::spawn(forward_output(stderr, true));
+                }
+                tokio::select! {
+                    status = child.wait() => match status {
+                        Ok(status) => warn!("PO token server exited with {}", status),
+                        Err(why) => error!("Failed to wait for PO token server: {:?}", why),
+                    },
+                    _ = SHUTDOWN.notified() => {
+                        if let Err(why) = child.kill().await {
+                            error!("Failed to stop PO token server: {:?}", why);
+                        }
+                        info!("Stopped PO token server");
+                        return;
+                    }
+                }
+            }
+            Err(why) => error!("Failed to start PO token server: {:?}", why),
+        }
+        HEALTHY.store(false, Ordering::Relaxed);
+        warn!("Restarting PO token server in {:?}", restart_delay);
+        tokio::select! {
+            _ = sleep(restart_delay) => {}
+            _ = SHUTDOWN.notified() => return,
+        }
+    }
+}
+
+/// Stops the supervised PO token server, `kill_on_drop` doesn't run when the process exits.
+pub fn shutdown() {
+    SHUTDOWN.notify_one();
+}
+
+async fn forward_output(output: impl AsyncRead + Unpin, stderr: bool) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if stderr {
+            warn!("PO token server: {}", line);
+        } else {
+            info!("PO token server: {}", line);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use super::{TRACK_METADATA, get_http_client, play_query};
+use crate::CONFIG;
+use crate::commands::{Context, Error};
+use crate::config::RadioStation;
+use crate::utils::message::{error_reply, info_message, info_reply, send_message, send_reply};
+use serenity::all::{ChannelId, Http};
+use songbird::tracks::{PlayMode, TrackHandle};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_FAILURES: u64 = 5;
+
+/// Finds a saved station by its name or URL.
+pub fn find_station(name_or_url: &str) -> Option<RadioStation> {
+    CONFIG
+        .get()
+        .unwrap()
+        .features
+        .music_player
+        .radio
+        .stations
+        .iter()
+        .find(|station| {
+            station.name.eq_ignore_ascii_case(name_or_url) || station.url == name_or_url
+        })
+        .cloned()
+}
+
+/// Extracts the song title from an ICY metadata block (`StreamTitle='...';`).
+pub fn parse_stream_title(metadata: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(metadata);
+    let text = text.trim_end_matches('\0');
+    let start = text.find("StreamTitle='")? + "StreamTitle='".len();
+    let value = &text[start..];
+    // Titles aren't escaped and can contain `';` themselves, so the value only ends where the
+    // block does or the next `Key='` starts.
+    let end = value
+        .match_indices("';")
+        .map(|(index, _)| index)
+        .find(|index| {
+            let rest = value[index + 2..].trim();
+            rest.is_empty()
+                || rest.split_once("='").is_some_and(|(key, _)| {
+                    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric())
+                })
+        })
+        .unwrap_or(value.len());
+    // Some servers double quotes like SQL does.
+    let title = value[..end]
+        .trim_end_matches('\'')
+        .replace("''", "'")
+        .trim()
+        .to_string();
+    (!title.is_empty()).then_some(title)
+}
+
+/// Splits an ICY stream into audio, which is skipped, and metadata blocks.
+struct IcyReader {
+    metaint: usize,
+    audio_left: usize,
+    metadata_len: Option<usize>,
+    metadata: Vec<u8>,
+}
+
+impl IcyReader {
+    fn new(metaint: usize) -> IcyReader {
+        IcyReader {
+            metaint,
+            audio_left: metaint,
+            metadata_len: None,
+            metadata: vec![],
+        }
+    }
+
+    /// Feeds a chunk of the stream, returning the titles of completed metadata blocks.
+    fn push(&mut self, mut data: &[u8]) -> Vec<String> {
+        let mut titles = vec![];
+        while !data.is_empty() {
+            if self.audio_left > 0 {
+                let skipped = self.audio_left.min(data.len());
+                self.audio_left -= skipped;
+                data = &data[skipped..];
+                continue;
+            }
+            match self.metadata_len {
+                None => {
+                    // The length byte counts blocks of 16 bytes.
+                    let len = data[0] as usize * 16;
+                    data = &data[1..];
+                    if len == 0 {
+                        self.audio_left = self.metaint;
+                    } else {
+                        self.metadata_len = Some(len);
+                        self.metadata.clear();
+                    }
+                }
+                Some(len) => {
+                    let read = (len - self.metadata.len()).min(data.len());
+                    self.metadata.extend_from_slice(&data[..read]);
+                    data = &data[read..];
+                    if self.metadata.len() == len {
+                        titles.extend(parse_stream_title(&self.metadata));
+                        self.metadata_len = None;
+                        self.audio_left = self.metaint;
+                    }
+                }
+            }
+        }
+        titles
+    }
+}
+
+enum IcyError {
+    Unsupported,
+    Request(reqwest::Error),
+}
+
+/// Follows the song titles of a playing radio stream until its track ends.
+pub fn watch(track: TrackHandle, url: String, channel_id: ChannelId, http: Arc<Http>) {
+    tokio::spawn(async move {
+        // Queued tracks stay paused until it's their turn.
+        loop {
+            match track.get_info().await {
+                Ok(state) if state.playing == PlayMode::Play => break,
+                Ok(state) if state.playing.is_done() => return,
+                Ok(_) => sleep(POLL_INTERVAL).await,
+                Err(_) => return,
+            }
+        }
+        let mut failures = 0;
+        loop {
+            let result = tokio::select! {
+                result = read_titles(&url, track.uuid(), channel_id, &http) => result,
+                _ = wait_until_done(&track) => return,
+            };
+            match result {
+                Ok(()) => failures = 0,
+                Err(IcyError::Unsupported) => {
+                    debug!("{} doesn't send ICY metadata", url);
+                    return;
+                }
+                Err(IcyError::Request(why)) => {
+                    debug!("Failed to read ICY metadata from {}: {:?}", url, why);
+                    failures += 1;
+                }
+            }
+            if failures >= MAX_FAILURES {
+                warn!("Giving up reading ICY metadata from {}", url);
+                return;
+            }
+            sleep(Duration::from_secs(5 * failures)).await;
+        }
+    });
+}
+
+async fn wait_until_done(track: &TrackHandle) {
+    while track
+        .get_info()
+        .await
+        .is_ok_and(|state| !state.playing.is_done())
+    {
+        sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn read_titles(
+    url: &str,
+    uuid: Uuid,
+    channel_id: ChannelId,
+    http: &Arc<Http>,
+) -> Result<(), IcyError> {
+    let mut response = get_http_client()
+        .await
+        .get(url)
+        .header("Icy-MetaData", "1")
+        .send()
+        .await
+        .map_err(IcyError::Request)?;
+    let metaint = response
+        .headers()
+        .get("icy-metaint")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok())
+        .filter(|metaint| *metaint > 0)
+        .ok_or(IcyError::Unsupported)?;
+    let mut reader = IcyReader::new(metaint);
+    while let Some(chunk) = response.chunk().await.map_err(IcyError::Request)? {
+        for title in reader.push(&chunk) {
+            update_title(uuid, title, channel_id, http).await;
+        }
+    }
+    Ok(())
+}
+
+async fn update_title(uuid: Uuid, title: String, channel_id: ChannelId, http: &Arc<Http>) {
+    let station = {
+        let mut metadatas = TRACK_METADATA.lock().await;
+        let Some(metadata) = metadatas.get_mut(&uuid).map(|track| &mut track.metadata) else {
+            return;
+        };
+        if metadata.title.as_ref() == Some(&title) {
+            return;
+        }
+        metadata.title = Some(title.clone());
+        metadata.channel.clone().unwrap_or_default()
+    };
+    debug!("{} is now playing {}", station, title);
+    if CONFIG
+        .get()
+        .unwrap()
+        .features
+        .music_player
+        .radio
+        .announce_title_changes
+    {
+        send_message(
+            http,
+            &channel_id,
+            info_message(
+                None,
+                format!("Now playing on **{}**: {}", station, title),
+                Some("Radio".to_string()),
+            )
+            .await,
+        )
+        .await;
+    }
+}
+
+async fn autocomplete_station<'a>(
+    _ctx: Context<'_>,
+    partial: &'a str,
+) -> impl Iterator<Item = String> + 'a {
+    let partial = partial.to_lowercase();
+    CONFIG
+        .get()
+        .unwrap()
+        .features
+        .music_player
+        .radio
+        .stations
+        .iter()
+        .map(|station| station.name.clone())
+        .filter(move |name| name.to_lowercase().contains(&partial))
+        .collect::<Vec<String>>()
+        .into_iter()
+}
+
+/// Plays a saved radio station, or lists them
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn radio(
+    ctx: Context<'_>,
+    #[description = "The station to play"]
+    #[autocomplete = "autocomplete_station"]
+    #[rest]
+    station: Option<String>,
+) -> Result<(), Error> {
+    let stations = &CONFIG.get().unwrap().features.music_player.radio.stations;
+    let Some(name) = station else {
+        let mut list = "## Stations\n".to_string();
+        if stations.is_empty() {
+            list.push_str("No stations have been saved in the config.");
+        }
+        for station in stations {
+            list.push_str(&format!("- [{}]({})\n", station.name, station.url));
+        }
+        send_reply(
+            &ctx,
+            info_reply(
+                Some(ctx.serenity_context()),
+                list,
+                Some("Radio".to_string()),
+            )
+            .await,
+        )
+        .await;
+        return Ok(());
+    };
+    match find_station(&name) {
+        Some(station) => play_query(ctx, station.url, Some("radio".to_string())).await,
+        None => {
+            send_reply(
+                &ctx,
+                error_reply(
+                    Some(ctx.serenity_context()),
+                    format!("No station named `{}` was found.", name),
+                    Some("Radio".to_string()),
+                )
+                .await,
+            )
+            .await;
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A metadata block with its length byte, padded to 16 bytes like servers do.
+    fn metadata_block(metadata: &str) -> Vec<u8> {
+        let mut block = metadata.as_bytes().to_vec();
+        block.resize(metadata.len().div_ceil(16) * 16, 0);
+        block.insert(0, (block.len() / 16) as u8);
+        block
+    }
+
+    #[test]
+    fn parses_stream_titles() {
+        let title = |metadata: &str| parse_stream_title(metadata.as_bytes());
+        assert_eq!(
+            title("StreamTitle='Artist - Song';StreamUrl='';\0\0").as_deref(),
+            Some("Artist - Song")
+        );
+        assert_eq!(title("StreamTitle='It''s';").as_deref(), Some("It's"));
+        assert_eq!(
+            title("StreamTitle='Rock';n';Roll';StreamUrl='https://radio.example';").as_deref(),
+            Some("Rock';n';Roll")
+        );
+        assert_eq!(
+            title("StreamTitle='Unterminated").as_deref(),
+            Some("Unterminated")
+        );
+        assert_eq!(title("StreamTitle='';StreamUrl='';"), None);
+        assert_eq!(title("StreamUrl='https://radio.example';"), None);
+    }
+
+    #[test]
+    fn reads_metadata_between_audio() {
+        let mut reader = IcyReader::new(8);
+        let mut stream = vec![0xAA; 8];
+        stream.extend(metadata_block("StreamTitle='First';"));
+        stream.extend([0xAA; 8]);
+        stream.extend(metadata_block("StreamTitle='Second';"));
+        assert_eq!(reader.push(&stream), ["First", "Second"]);
+    }
+
+    #[test]
+    fn reads_metadata_split_across_chunks() {
+        let mut stream = vec![0xAA; 4];
+        stream.extend(metadata_block("StreamTitle='Split title';"));
+        stream.extend([0xAA; 4]);
+        for split in 1..stream.len() {
+            let mut reader = IcyReader::new(4);
+            let mut titles = reader.push(&stream[..split]);
+            titles.extend(reader.push(&stream[split..]));
+            assert_eq!(titles, ["Split title"], "split at {}", split);
+        }
+        // Byte by byte.
+        let mut reader = IcyReader::new(4);
+        let titles: Vec<String> = stream
+            .iter()
+            .flat_map(|byte| reader.push(&[*byte]))
+            .collect();
+        assert_eq!(titles, ["Split title"]);
+    }
+
+    #[test]
+    fn skips_empty_metadata_blocks() {
+        let mut reader = IcyReader::new(4);
+        let mut stream = vec![0xAA; 4];
+        // No metadata this time, the audio goes on right after the length byte.
+        stream.push(0);
+        stream.extend([0xAA; 4]);
+        stream.extend(metadata_block("StreamTitle='After';"));
+        assert_eq!(reader.push(&stream), ["After"]);
+    }
+
+    #[test]
+    fn ignores_streams_without_titles() {
+        let mut reader = IcyReader::new(4);
+        let mut stream = vec![];
+        for _ in 0..3 {
+            stream.extend([0xAA; 4]);
+            stream.extend(metadata_block("StreamUrl='https://radio.example';"));
+            stream.extend([0xAA; 4]);
+            stream.push(0);
+        }
+        assert!(reader.push(&stream).is_empty());
+    }
+}
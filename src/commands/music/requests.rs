@@ -0,0 +1,293 @@
+use super::{
+    Request, add_to_queue, join_vc, player, query_track, queue_space, settings, track_link,
+};
+use crate::CONFIG;
+use crate::commands::{Context, Error};
+use crate::utils::message::{error_embed, error_reply, info_embed, info_reply, send_reply};
+use serenity::all::{
+    ChannelId, ChannelType, CreateMessage, GuildChannel, Message, MessageId, UserId,
+};
+use serenity::client::Context as SerenityContext;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+/// When each user last made a request, to rate limit them.
+static LAST_REQUESTS: LazyLock<Mutex<HashMap<UserId, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Replies in the request channel, deleting the reply after a while.
+async fn notice(ctx: &SerenityContext, channel_id: ChannelId, content: String, is_error: bool) {
+    let embed = if is_error {
+        error_embed(Some(ctx), Some("Music".to_string()), Some(content)).await
+    } else {
+        info_embed(Some(ctx), Some("Music".to_string()), Some(content)).await
+    };
+    let message = match channel_id
+        .send_message(ctx, CreateMessage::new().embed(embed))
+        .await
+    {
+        Ok(message) => message,
+        Err(why) => {
+            warn!("Failed to send a request channel notice: {:?}", why);
+            return;
+        }
+    };
+    let delay = CONFIG
+        .get()
+        .unwrap()
+        .features
+        .music_player
+        .request_channel
+        .notice_delete_secs;
+    let http = ctx.http.clone();
+    tokio::spawn(async move {
+        sleep(Duration::from_secs(delay)).await;
+        let _ = message.delete(&http).await;
+    });
+}
+
+/// Whether the user made a request too recently, recording this one otherwise.
+async fn rate_limited(user_id: UserId) -> bool {
+    let cooldown = Duration::from_secs(
+        CONFIG
+            .get()
+            .unwrap()
+            .features
+            .music_player
+            .request_channel
+            .cooldown_secs,
+    );
+    let now = Instant::now();
+    let mut last_requests = LAST_REQUESTS.lock().await;
+    if last_requests
+        .get(&user_id)
+        .is_some_and(|last| now.duration_since(*last) < cooldown)
+    {
+        return true;
+    }
+    last_requests.retain(|_, last| now.duration_since(*last) < cooldown);
+    last_requests.insert(user_id, now);
+    false
+}
+
+/// Plays messages posted in a guild's request channel, deleting them afterwards.
+pub async fn handle_message(ctx: &SerenityContext, msg: &Message) {
+    let Some(guild_id) = msg.guild_id else {
+        return;
+    };
+    if msg.author.bot {
+        return;
+    }
+    let settings = settings::get(guild_id).await;
+    if settings.request_channel != Some(msg.channel_id.get()) {
+        return;
+    }
+    // Prefix commands keep working in the request channel.
+    if msg
+        .content
+        .starts_with(&CONFIG.get().unwrap().general.prefix)
+    {
+        return;
+    }
+    if let Err(why) = msg.delete(ctx).await {
+        warn!("Failed to delete a request: {:?}", why);
+    }
+    if let Some(panel) = settings.request_panel
+        && let Err(why) =
+            player::restore_panel(&ctx.http, guild_id, msg.channel_id, MessageId::new(panel)).await
+    {
+        debug!("Failed to restore the request channel panel: {:?}", why);
+    }
+    let query = msg.content.trim().to_string();
+    if query.is_empty() {
+        return;
+    }
+    if rate_limited(msg.author.id).await {
+        notice(
+            ctx,
+            msg.channel_id,
+            format!("{}, wait a few seconds between requests.", msg.author),
+            true,
+        )
+        .await;
+        return;
+    }
+    debug!(
+        "Received request {} by {} ({})",
+        query, msg.author.name, msg.author.id
+    );
+    let request = Request {
+        guild_id,
+        channel_id: msg.channel_id,
+        user_id: msg.author.id,
+        serenity: ctx.clone(),
+    };
+    let manager = songbird::get(ctx).await.unwrap().clone();
+    let in_vc = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.current_channel().is_some(),
+        None => false,
+    };
+    if !in_vc && let Err(why) = join_vc(&request, manager.clone()).await {
+        notice(
+            ctx,
+            msg.channel_id,
+            format!("{}, failed to join voice channel: {}", msg.author, why),
+            true,
+        )
+        .await;
+        return;
+    }
+    if queue_space(&manager, guild_id).await == 0 {
+        notice(ctx, msg.channel_id, "The queue is full.".to_string(), true).await;
+        return;
+    }
+    let track = match query_track(query, None).await {
+        Ok(track) => track,
+        Err(why) => {
+            debug!("Failed to resolve request: {}", why);
+            notice(ctx, msg.channel_id, why.user_message(), true).await;
+            return;
+        }
+    };
+    let link = track_link(&track.metadata);
+    let (_, playing) = add_to_queue(&request, &manager, track).await;
+    let content = if playing {
+        format!("Playing track: {}", link)
+    } else {
+        format!("Added track to queue: {}", link)
+    };
+    notice(ctx, msg.channel_id, content, false).await;
+}
+
+/// Sets up a channel where every message is played as a track
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    rename = "request-channel",
+    required_permissions = "MANAGE_GUILD",
+    subcommands("set", "disable"),
+    subcommand_required
+)]
+pub async fn request_channel(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Removes the panel of the current request channel.
+async fn remove_panel(ctx: &Context<'_>, settings: &settings::GuildSettings) {
+    let (Some(channel), Some(panel)) = (settings.request_channel, settings.request_panel) else {
+        return;
+    };
+    let panel = MessageId::new(panel);
+    player::forget_panel(ctx.guild_id().unwrap(), panel).await;
+    if let Err(why) = ChannelId::new(channel).delete_message(ctx, panel).await {
+        debug!("Failed to delete the request channel panel: {:?}", why);
+    }
+}
+
+/// Makes a channel the request channel, pinning a player panel in it
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn set(
+    ctx: Context<'_>,
+    #[description = "The channel to take requests in"]
+    #[channel_types("Text")]
+    channel: GuildChannel,
+) -> Result<(), Error> {
+    if channel.kind != ChannelType::Text {
+        send_reply(
+            &ctx,
+            error_reply(
+                Some(ctx.serenity_context()),
+                "The request channel must be a text channel.".to_string(),
+                Some("Music".to_string()),
+            )
+            .await,
+        )
+        .await;
+        return Ok(());
+    }
+    let guild_id = ctx.guild_id().unwrap();
+    remove_panel(&ctx, &settings::get(guild_id).await).await;
+    let manager = songbird::get(ctx.serenity_context()).await.unwrap().clone();
+    let panel =
+        match player::send_panel(ctx.serenity_context(), &manager, guild_id, channel.id).await {
+            Ok(panel) => panel,
+            Err(why) => {
+                debug!("Failed to send the request channel panel: {:?}", why);
+                send_reply(
+                    &ctx,
+                    error_reply(
+                        Some(ctx.serenity_context()),
+                        format!("I can't send messages in {}.", channel),
+                        Some("Music".to_string()),
+                    )
+                    .await,
+                )
+                .await;
+                return Ok(());
+            }
+        };
+    let mut reply = format!(
+        "Messages sent in {} will now be played, prefix commands still work there.",
+        channel
+    );
+    if let Err(why) = panel.pin(ctx).await {
+        debug!("Failed to pin the request channel panel: {:?}", why);
+        reply.push_str(
+            "\n\n-# I couldn't pin the player panel, I need the Manage Messages permission.",
+        );
+    }
+    settings::update(guild_id, |settings| {
+        settings.request_channel = Some(channel.id.get());
+        settings.request_panel = Some(panel.id.get());
+    })
+    .await;
+    send_reply(
+        &ctx,
+        info_reply(
+            Some(ctx.serenity_context()),
+            reply,
+            Some("Music".to_string()),
+        )
+        .await,
+    )
+    .await;
+    Ok(())
+}
+
+/// Stops taking requests in the request channel
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn disable(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().unwrap();
+    remove_panel(&ctx, &settings::get(guild_id).await).await;
+    settings::update(guild_id, |settings| {
+        settings.request_channel = None;
+        settings.request_panel = None;
+    })
+    .await;
+    send_reply(
+        &ctx,
+        info_reply(
+            Some(ctx.serenity_context()),
+            "The request channel has been disabled.".to_string(),
+            Some("Music".to_string()),
+        )
+        .await,
+    )
+    .await;
+    Ok(())
+}
@@ -0,0 +1,43 @@
+use crate::storage;
+use serde::{Deserialize, Serialize};
+use serenity::all::GuildId;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tokio::sync::Mutex;
+use tracing::error;
+
+const SETTINGS_FILE: &str = "guild_settings";
+
+/// Music settings of each guild, changed with commands rather than the config file.
+static SETTINGS: LazyLock<Mutex<HashMap<u64, GuildSettings>>> =
+    LazyLock::new(|| Mutex::new(storage::load(SETTINGS_FILE)));
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct GuildSettings {
+    /// Channel where every message is played as a track.
+    pub request_channel: Option<u64>,
+    /// Pinned player panel of the request channel.
+    pub request_panel: Option<u64>,
+    /// Channel tracks are announced in, instead of the one they were requested in.
+    pub announce_channel: Option<u64>,
+    pub announcements_disabled: bool,
+}
+
+pub async fn get(guild_id: GuildId) -> GuildSettings {
+    SETTINGS
+        .lock()
+        .await
+        .get(&guild_id.get())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Changes the settings of a guild and saves them.
+pub async fn update(guild_id: GuildId, change: impl FnOnce(&mut GuildSettings)) {
+    let mut settings = SETTINGS.lock().await;
+    change(settings.entry(guild_id.get()).or_default());
+    if let Err(why) = storage::save(SETTINGS_FILE, &*settings) {
+        error!("Failed to save guild settings: {:?}", why);
+    }
+}
@@ -0,0 +1,514 @@
+use super::radio::find_station;
+use super::ytdl::{self, YtdlError};
+use super::{get_http_client, use_pot};
+use crate::CONFIG;
+use reqwest::header::{CONTENT_LENGTH, CONTENT_TYPE, HeaderMap};
+use reqwest::{Response, Url};
+use serenity::async_trait;
+use songbird::input::{AuxMetadata, File, HttpRequest, Input};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+use std::time::Duration;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::{MetadataOptions, StandardTagKey};
+use symphonia::core::probe::Hint;
+use tracing::{debug, warn};
+
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+const AUDIO_EXTENSIONS: [&str; 10] = [
+    "mp3", "ogg", "opus", "flac", "wav", "m4a", "aac", "webm", "mp4", "alac",
+];
+/// Sites yt-dlp handles, their links go to it without probing them first.
+const YTDL_HOSTS: [&str; 9] = [
+    "youtube.com",
+    "youtu.be",
+    "soundcloud.com",
+    "bandcamp.com",
+    "vimeo.com",
+    "twitch.tv",
+    "dailymotion.com",
+    "mixcloud.com",
+    "nicovideo.jp",
+];
+
+/// Resolvers in the order they're tried when no source is given explicitly.
+static RESOLVERS: LazyLock<Vec<Box<dyn SourceResolver>>> = LazyLock::new(|| {
+    vec![
+        Box::new(LocalResolver),
+        Box::new(RadioResolver),
+        Box::new(HttpResolver),
+        Box::new(YtdlResolver),
+    ]
+});
+
+/// A track ready to be enqueued.
+pub struct ResolvedTrack {
+    pub input: Input,
+    pub metadata: AuxMetadata,
+    /// Endless streams like internet radio.
+    pub live: bool,
+}
+
+#[derive(Debug)]
+pub enum SourceError {
+    Ytdl(YtdlError),
+    UnknownSource(String),
+    NotFound(String),
+    Forbidden(String),
+    Http(String),
+}
+
+impl SourceError {
+    pub fn user_message(&self) -> String {
+        match self {
+            SourceError::Ytdl(why) => why.user_message(),
+            SourceError::UnknownSource(name) => format!(
+                "Unknown source `{}`, available sources: `{}`",
+                name,
+                names().join("`, `")
+            ),
+            SourceError::NotFound(what) => format!("Couldn't find `{}`.", what),
+            SourceError::Forbidden(why) => why.clone(),
+            SourceError::Http(_) => "Failed to fetch the audio from that URL.".to_string(),
+        }
+    }
+}
+
+impl fmt::Display for SourceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceError::Ytdl(why) => write!(f, "{}", why),
+            SourceError::UnknownSource(name) => write!(f, "unknown source '{}'", name),
+            SourceError::NotFound(what) => write!(f, "'{}' not found", what),
+            SourceError::Forbidden(why) => write!(f, "{}", why),
+            SourceError::Http(why) => write!(f, "HTTP error: {}", why),
+        }
+    }
+}
+
+impl std::error::Error for SourceError {}
+
+impl From<YtdlError> for SourceError {
+    fn from(why: YtdlError) -> Self {
+        SourceError::Ytdl(why)
+    }
+}
+
+/// What a resolver learned while matching a query, so `resolve` doesn't fetch it again.
+#[derive(Default)]
+pub struct Probe {
+    pub response: Option<Response>,
+}
+
+/// Turns a `/play` query into something songbird can play.
+#[async_trait]
+pub trait SourceResolver: Send + Sync {
+    /// Name used to pick this resolver with the `source` option of `/play`.
+    fn name(&self) -> &'static str;
+    /// Whether this resolver should handle `query` when no source is given.
+    async fn matches(&self, query: &str) -> Option<Probe>;
+    /// Resolves `query`, with the probe from `matches` unless the source was given explicitly.
+    async fn resolve(&self, query: &str, probe: Probe) -> Result<ResolvedTrack, SourceError>;
+}
+
+/// Names of all registered resolvers.
+pub fn names() -> Vec<&'static str> {
+    RESOLVERS.iter().map(|resolver| resolver.name()).collect()
+}
+
+/// Resolves `query` with the resolver named `source`, or the first one matching the query.
+pub async fn resolve(source: Option<&str>, query: &str) -> Result<ResolvedTrack, SourceError> {
+    let (resolver, probe) = match source {
+        Some(source) => (
+            RESOLVERS
+                .iter()
+                .find(|resolver| resolver.name().eq_ignore_ascii_case(source))
+                .ok_or_else(|| SourceError::UnknownSource(source.to_string()))?,
+            Probe::default(),
+        ),
+        None => {
+            let mut matching = None;
+            for resolver in RESOLVERS.iter() {
+                if let Some(probe) = resolver.matches(query).await {
+                    matching = Some((resolver, probe));
+                    break;
+                }
+            }
+            // The yt-dlp resolver matches everything, so there's always one.
+            matching.unwrap()
+        }
+    };
+    debug!("Resolving '{}' with the {} source", query, resolver.name());
+    resolver.resolve(query, probe).await
+}
+
+fn parse_http_url(query: &str) -> Option<Url> {
+    Url::parse(query.trim())
+        .ok()
+        .filter(|url| url.scheme() == "http" || url.scheme() == "https")
+}
+
+/// Requests `url` without reading the body, asking for ICY metadata like radio players do.
+async fn probe(url: &Url) -> Result<Response, reqwest::Error> {
+    get_http_client()
+        .await
+        .get(url.clone())
+        .header("Icy-MetaData", "1")
+        .timeout(PROBE_TIMEOUT)
+        .send()
+        .await
+}
+
+fn is_audio(headers: &HeaderMap) -> bool {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("audio/") || value.starts_with("application/ogg"))
+}
+
+/// Whether the headers describe an endless stream: ICY headers, or audio without a length.
+fn is_live_stream(headers: &HeaderMap) -> bool {
+    headers.keys().any(|name| name.as_str().starts_with("icy-"))
+        || (is_audio(headers) && !headers.contains_key(CONTENT_LENGTH))
+}
+
+fn is_ytdl_host(url: &Url) -> bool {
+    url.host_str().is_some_and(|host| {
+        YTDL_HOSTS
+            .iter()
+            .any(|ytdl| host == *ytdl || host.ends_with(&format!(".{}", ytdl)))
+    })
+}
+
+fn has_audio_extension(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Plays anything yt-dlp supports, searching YouTube for plain queries.
+pub struct YtdlResolver;
+
+#[async_trait]
+impl SourceResolver for YtdlResolver {
+    fn name(&self) -> &'static str {
+        "ytdl"
+    }
+
+    async fn matches(&self, _query: &str) -> Option<Probe> {
+        Some(Probe::default())
+    }
+
+    async fn resolve(&self, query: &str, _probe: Probe) -> Result<ResolvedTrack, SourceError> {
+        let client = get_http_client().await;
+        let mut src = ytdl::options().source(client, query.to_string(), use_pot().await);
+        let metadata = ytdl::fetch_metadata(&mut src, query).await?;
+        Ok(ResolvedTrack {
+            input: src.into(),
+            metadata,
+            live: false,
+        })
+    }
+}
+
+/// Plays direct links to audio files and streams, streams being played as live input.
+pub struct HttpResolver;
+
+#[async_trait]
+impl SourceResolver for HttpResolver {
+    fn name(&self) -> &'static str {
+        "http"
+    }
+
+    async fn matches(&self, query: &str) -> Option<Probe> {
+        let url = parse_http_url(query).filter(|url| !is_ytdl_host(url))?;
+        if has_audio_extension(url.path()) {
+            return Some(Probe::default());
+        }
+        // Stream URLs rarely have an extension, only the response tells them apart from pages.
+        let response = probe(&url).await.ok()?;
+        (is_audio(response.headers()) || is_live_stream(response.headers())).then_some(Probe {
+            response: Some(response),
+        })
+    }
+
+    async fn resolve(&self, query: &str, probe: Probe) -> Result<ResolvedTrack, SourceError> {
+        let url = parse_http_url(query).ok_or_else(|| SourceError::NotFound(query.to_string()))?;
+        let client = get_http_client().await;
+        let response = match probe.response {
+            Some(response) => response,
+            None => self::probe(&url)
+                .await
+                .map_err(|why| SourceError::Http(why.to_string()))?,
+        };
+        if !response.status().is_success() {
+            return Err(SourceError::Http(format!(
+                "{} returned {}",
+                url,
+                response.status()
+            )));
+        }
+        let headers = response.headers();
+        if is_live_stream(headers) {
+            let station = headers
+                .get("icy-name")
+                .and_then(|name| name.to_str().ok())
+                .filter(|name| !name.trim().is_empty())
+                .map(|name| name.trim().to_string())
+                .unwrap_or_else(|| url.host_str().unwrap_or(url.as_str()).to_string());
+            return Ok(live_track(client, url, station));
+        }
+        let title = url
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|name| !name.is_empty())
+            .unwrap_or(url.as_str())
+            .to_string();
+        Ok(ResolvedTrack {
+            input: HttpRequest::new(client, url.to_string()).into(),
+            metadata: AuxMetadata {
+                title: Some(title),
+                source_url: Some(url.to_string()),
+                ..Default::default()
+            },
+            live: false,
+        })
+    }
+}
+
+/// Input and metadata of an endless stream, named after its station.
+fn live_track(client: reqwest::Client, url: Url, station: String) -> ResolvedTrack {
+    ResolvedTrack {
+        input: HttpRequest::new(client, url.to_string()).into(),
+        metadata: AuxMetadata {
+            title: Some(station.clone()),
+            channel: Some(station),
+            source_url: Some(url.to_string()),
+            ..Default::default()
+        },
+        live: true,
+    }
+}
+
+/// Plays the configured radio stations and streams from the configured radio hosts.
+pub struct RadioResolver;
+
+#[async_trait]
+impl SourceResolver for RadioResolver {
+    fn name(&self) -> &'static str {
+        "radio"
+    }
+
+    async fn matches(&self, query: &str) -> Option<Probe> {
+        let radio_hosts = &CONFIG
+            .get()
+            .unwrap()
+            .features
+            .music_player
+            .sources
+            .radio_hosts;
+        (parse_http_url(query).is_some_and(|url| {
+            url.host_str()
+                .is_some_and(|host| radio_hosts.iter().any(|radio| radio == host))
+        }) || find_station(query).is_some())
+        .then(Probe::default)
+    }
+
+    async fn resolve(&self, query: &str, _probe: Probe) -> Result<ResolvedTrack, SourceError> {
+        // Stations are looked up first, since they're usually played by name.
+        let (url, station) = match find_station(query) {
+            Some(station) => {
+                let url = parse_http_url(&station.url)
+                    .ok_or_else(|| SourceError::NotFound(station.url.clone()))?;
+                (url, station.name)
+            }
+            None => {
+                let url = parse_http_url(query)
+                    .ok_or_else(|| SourceError::NotFound(query.to_string()))?;
+                let host = url.host_str().unwrap_or(url.as_str()).to_string();
+                (url, host)
+            }
+        };
+        Ok(live_track(get_http_client().await, url, station))
+    }
+}
+
+/// Plays files from the configured local library.
+pub struct LocalResolver;
+
+impl LocalResolver {
+    /// Resolves `query` inside the library, refusing paths that escape it.
+    pub fn library_path(query: &str) -> Result<PathBuf, SourceError> {
+        let config = CONFIG.get().unwrap();
+        let library = match &config.features.music_player.sources.library_path {
+            Some(library) => library,
+            None => {
+                return Err(SourceError::Forbidden(
+                    "Playing local files is disabled.".to_string(),
+                ));
+            }
+        };
+        let relative = query.trim().trim_start_matches("file://");
+        let library = Path::new(library)
+            .canonicalize()
+            .map_err(|_| SourceError::NotFound(library.to_string()))?;
+        let path = library
+            .join(relative.trim_start_matches('/'))
+            .canonicalize()
+            .map_err(|_| SourceError::NotFound(relative.to_string()))?;
+        if !path.starts_with(&library) || !path.is_file() {
+            return Err(SourceError::NotFound(relative.to_string()));
+        }
+        Ok(path)
+    }
+}
+
+#[async_trait]
+impl SourceResolver for LocalResolver {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn matches(&self, query: &str) -> Option<Probe> {
+        query.starts_with("file://").then(Probe::default)
+    }
+
+    async fn resolve(&self, query: &str, _probe: Probe) -> Result<ResolvedTrack, SourceError> {
+        let path = Self::library_path(query)?;
+        let probe_path = path.clone();
+        let metadata = tokio::task::spawn_blocking(move || probe_file(&probe_path))
+            .await
+            .unwrap_or_else(|why| {
+                warn!("Failed to probe {}: {:?}", query, why);
+                AuxMetadata::default()
+            });
+        Ok(ResolvedTrack {
+            input: File::new(path.clone()).into(),
+            metadata: AuxMetadata {
+                title: metadata.title.or_else(|| {
+                    path.file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                }),
+                // Kept relative to the library so the track can be queued again later.
+                source_url: Some(format!(
+                    "file://{}",
+                    query.trim().trim_start_matches("file://")
+                )),
+                ..metadata
+            },
+            live: false,
+        })
+    }
+}
+
+/// Reads the tags and duration of a local file.
+fn probe_file(path: &Path) -> AuxMetadata {
+    let mut metadata = AuxMetadata::default();
+    let Ok(file) = std::fs::File::open(path) else {
+        return metadata;
+    };
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(ext);
+    }
+    let Ok(mut probed) = symphonia::default::get_probe().format(
+        &hint,
+        MediaSourceStream::new(Box::new(file), Default::default()),
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    ) else {
+        return metadata;
+    };
+    if let Some(track) = probed.format.default_track() {
+        let params = &track.codec_params;
+        if let (Some(frames), Some(rate)) = (params.n_frames, params.sample_rate) {
+            metadata.duration = Some(Duration::from_secs_f64(frames as f64 / rate as f64));
+        }
+        metadata.sample_rate = params.sample_rate;
+        metadata.channels = params.channels.map(|channels| channels.count() as u8);
+    }
+    let revision = probed.format.metadata().current().cloned().or_else(|| {
+        probed
+            .metadata
+            .get()
+            .and_then(|meta| meta.current().cloned())
+    });
+    if let Some(revision) = revision {
+        for tag in revision.tags() {
+            match tag.std_key {
+                Some(StandardTagKey::TrackTitle) => metadata.title = Some(tag.value.to_string()),
+                Some(StandardTagKey::Artist) => metadata.artist = Some(tag.value.to_string()),
+                Some(StandardTagKey::Album) => metadata.album = Some(tag.value.to_string()),
+                _ => {}
+            }
+        }
+    }
+    metadata
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{HeaderName, HeaderValue};
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| {
+                (
+                    HeaderName::from_static(name),
+                    HeaderValue::from_static(value),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn sends_ytdl_sites_straight_to_ytdl() {
+        for url in [
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://youtu.be/dQw4w9WgXcQ",
+            "https://music.youtube.com/watch?v=dQw4w9WgXcQ",
+            "https://soundcloud.com/artist/track",
+            "https://artist.bandcamp.com/track/song",
+        ] {
+            assert!(is_ytdl_host(&parse_http_url(url).unwrap()), "{}", url);
+        }
+        for url in [
+            "https://stream.example.com/live",
+            "https://notyoutube.com/watch",
+            "https://youtube.com.example.com/stream",
+        ] {
+            assert!(!is_ytdl_host(&parse_http_url(url).unwrap()), "{}", url);
+        }
+    }
+
+    #[test]
+    fn detects_live_streams_from_headers() {
+        // Icecast with the metadata the probe asks for.
+        assert!(is_live_stream(&headers(&[
+            ("content-type", "audio/mpeg"),
+            ("icy-name", "Radio"),
+            ("icy-metaint", "16000"),
+        ])));
+        // Audio without a length never ends.
+        assert!(is_live_stream(&headers(&[("content-type", "audio/aac")])));
+        assert!(is_live_stream(&headers(&[(
+            "content-type",
+            "application/ogg"
+        )])));
+        // A finite file.
+        assert!(!is_live_stream(&headers(&[
+            ("content-type", "audio/mpeg"),
+            ("content-length", "4096"),
+        ])));
+        // A web page, like a YouTube link.
+        assert!(!is_live_stream(&headers(&[(
+            "content-type",
+            "text/html; charset=utf-8"
+        )])));
+    }
+}
@@ -0,0 +1,271 @@
+use super::{QueuedTrack, history};
+use crate::CONFIG;
+use crate::commands::{Context, Error};
+use crate::storage::{self, BackgroundSaver};
+use crate::utils::message::{info_reply, send_reply};
+use crate::utils::time::format_duration;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serenity::all::GuildId;
+use songbird::tracks::TrackState;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tracing::info;
+
+const STATS_FILE: &str = "stats";
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+const TOP_COUNT: usize = 5;
+
+/// Listening statistics of a guild by day (since the Unix epoch) and user.
+///
+/// Keeping daily aggregates instead of every play keeps the file small, lets retention drop
+/// whole days and lets a user's data be removed without touching anyone else's.
+type GuildStats = BTreeMap<i64, HashMap<u64, UserStats>>;
+
+static STATS: LazyLock<Mutex<HashMap<u64, GuildStats>>> =
+    LazyLock::new(|| Mutex::new(storage::load(STATS_FILE)));
+static SAVER: BackgroundSaver = BackgroundSaver::new(STATS_FILE);
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct TrackStats {
+    title: String,
+    plays: u32,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+struct UserStats {
+    plays: u32,
+    listened_secs: u64,
+    /// Plays by UTC hour of the day.
+    hours: [u32; 24],
+    /// Plays by track URL, or title when there's none.
+    tracks: HashMap<String, TrackStats>,
+}
+
+#[derive(Default)]
+struct Summary {
+    plays: u32,
+    listened_secs: u64,
+    hours: [u32; 24],
+    tracks: HashMap<String, TrackStats>,
+    requesters: HashMap<u64, u32>,
+}
+
+fn today() -> i64 {
+    Utc::now().timestamp().div_euclid(SECONDS_PER_DAY)
+}
+
+/// First day still within the retention period.
+fn first_kept_day() -> i64 {
+    today()
+        - CONFIG
+            .get()
+            .unwrap()
+            .features
+            .music_player
+            .stats
+            .retention_days
+        + 1
+}
+
+/// Counts a track that just ended towards its requester's statistics.
+pub(super) async fn record(guild_id: GuildId, track: &QueuedTrack, state: &TrackState) {
+    if !CONFIG.get().unwrap().features.music_player.stats.enabled || state.play_time.is_zero() {
+        return;
+    }
+    let started_at = Utc::now().timestamp() - state.play_time.as_secs() as i64;
+    let day = started_at.div_euclid(SECONDS_PER_DAY);
+    let hour = started_at.rem_euclid(SECONDS_PER_DAY) / 3600;
+    let title = track
+        .metadata
+        .title
+        .clone()
+        .unwrap_or_else(|| "Unknown track".to_string());
+    let key = track
+        .metadata
+        .source_url
+        .clone()
+        .unwrap_or_else(|| title.clone());
+    let mut stats = STATS.lock().await;
+    let days = stats.entry(guild_id.get()).or_default();
+    let user = days
+        .entry(day)
+        .or_default()
+        .entry(track.requester.get())
+        .or_default();
+    user.plays += 1;
+    user.listened_secs += state.play_time.as_secs();
+    user.hours[hour as usize] += 1;
+    let track_stats = user.tracks.entry(key).or_default();
+    track_stats.title = title;
+    track_stats.plays += 1;
+    // Drop the days that fell out of the retention period.
+    let first_kept_day = first_kept_day();
+    for days in stats.values_mut() {
+        days.retain(|day, _| *day >= first_kept_day);
+    }
+    stats.retain(|_, days| !days.is_empty());
+    SAVER.save(stats.clone());
+}
+
+/// Adds up the statistics of a guild, optionally only those of one user.
+async fn summarize(guild_id: GuildId, user_id: Option<u64>) -> Summary {
+    let mut summary = Summary::default();
+    let stats = STATS.lock().await;
+    let Some(days) = stats.get(&guild_id.get()) else {
+        return summary;
+    };
+    for users in days.range(first_kept_day()..).map(|(_, users)| users) {
+        for (user, user_stats) in users {
+            if user_id.is_some_and(|user_id| user_id != *user) {
+                continue;
+            }
+            summary.plays += user_stats.plays;
+            summary.listened_secs += user_stats.listened_secs;
+            *summary.requesters.entry(*user).or_default() += user_stats.plays;
+            for (hour, plays) in user_stats.hours.iter().enumerate() {
+                summary.hours[hour] += plays;
+            }
+            for (key, track) in &user_stats.tracks {
+                let total = summary.tracks.entry(key.clone()).or_default();
+                total.title.clone_from(&track.title);
+                total.plays += track.plays;
+            }
+        }
+    }
+    summary
+}
+
+fn format_summary(title: &str, summary: &Summary, show_requesters: bool) -> String {
+    let retention_days = CONFIG
+        .get()
+        .unwrap()
+        .features
+        .music_player
+        .stats
+        .retention_days;
+    let mut message = format!("## {}\n-# Last {} days\n", title, retention_days);
+    if summary.plays == 0 {
+        message.push_str("Nothing has been played yet.");
+        return message;
+    }
+    message.push_str(&format!(
+        "Tracks played: {}\nListening time: {}\n",
+        summary.plays,
+        format_duration(Duration::from_secs(summary.listened_secs))
+    ));
+    let mut tracks: Vec<(&String, &TrackStats)> = summary.tracks.iter().collect();
+    tracks.sort_by_key(|(_, track)| Reverse(track.plays));
+    message.push_str("### Top tracks\n");
+    for (index, (key, track)) in tracks.into_iter().take(TOP_COUNT).enumerate() {
+        let name = if key.starts_with("http") {
+            format!("[{}]({})", track.title, key)
+        } else {
+            track.title.clone()
+        };
+        message.push_str(&format!(
+            "{}. {} ({} plays)\n",
+            index + 1,
+            name,
+            track.plays
+        ));
+    }
+    if show_requesters {
+        let mut requesters: Vec<(&u64, &u32)> = summary.requesters.iter().collect();
+        requesters.sort_by_key(|(_, plays)| Reverse(**plays));
+        message.push_str("### Top requesters\n");
+        for (index, (user, plays)) in requesters.into_iter().take(TOP_COUNT).enumerate() {
+            message.push_str(&format!("{}. <@{}> ({} plays)\n", index + 1, user, plays));
+        }
+    }
+    let mut hours: Vec<(usize, u32)> = summary
+        .hours
+        .iter()
+        .copied()
+        .enumerate()
+        .filter(|(_, plays)| *plays > 0)
+        .collect();
+    hours.sort_by_key(|(_, plays)| Reverse(*plays));
+    message.push_str("### Busiest hours (UTC)\n");
+    for (hour, plays) in hours.into_iter().take(3) {
+        message.push_str(&format!("- {:02}:00 ({} plays)\n", hour, plays));
+    }
+    message
+}
+
+async fn reply(ctx: &Context<'_>, content: String) {
+    send_reply(
+        ctx,
+        info_reply(
+            Some(ctx.serenity_context()),
+            content,
+            Some("Stats".to_string()),
+        )
+        .await,
+    )
+    .await;
+}
+
+/// Shows listening statistics, the server's when no subcommand is given
+#[poise::command(
+    slash_command,
+    prefix_command,
+    guild_only,
+    subcommands("server", "me", "forget")
+)]
+pub async fn stats(ctx: Context<'_>) -> Result<(), Error> {
+    show_server_stats(ctx).await
+}
+
+/// Shows the top tracks, requesters and busiest hours of this server
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn server(ctx: Context<'_>) -> Result<(), Error> {
+    show_server_stats(ctx).await
+}
+
+async fn show_server_stats(ctx: Context<'_>) -> Result<(), Error> {
+    let summary = summarize(ctx.guild_id().unwrap(), None).await;
+    reply(&ctx, format_summary("Server stats", &summary, true)).await;
+    Ok(())
+}
+
+/// Shows what you listened to the most in this server
+#[poise::command(slash_command, prefix_command, guild_only)]
+pub async fn me(ctx: Context<'_>) -> Result<(), Error> {
+    let summary = summarize(ctx.guild_id().unwrap(), Some(ctx.author().id.get())).await;
+    let title = format!("Stats of {}", ctx.author().display_name());
+    reply(&ctx, format_summary(&title, &summary, false)).await;
+    Ok(())
+}
+
+/// Deletes your statistics and history in every server
+#[poise::command(slash_command, prefix_command, ephemeral)]
+pub async fn forget(ctx: Context<'_>) -> Result<(), Error> {
+    let user_id = ctx.author().id;
+    {
+        let mut stats = STATS.lock().await;
+        for days in stats.values_mut() {
+            for users in days.values_mut() {
+                users.remove(&user_id.get());
+            }
+            days.retain(|_, users| !users.is_empty());
+        }
+        stats.retain(|_, days| !days.is_empty());
+        SAVER.save(stats.clone());
+    }
+    history::forget_user(user_id).await;
+    info!(
+        "Deleted the listening data of {} ({})",
+        ctx.author().name,
+        user_id
+    );
+    reply(
+        &ctx,
+        "Your listening statistics and history have been deleted.".to_string(),
+    )
+    .await;
+    Ok(())
+}
@@ -0,0 +1,475 @@
+use crate::CONFIG;
+use crate::config::Config;
+use crate::metrics;
+use reqwest::Client as HttpClient;
+use songbird::input::{AudioStreamError, AuxMetadata, Compose, YoutubeDl};
+use std::fmt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+use tokio::time::{sleep, timeout};
+use tracing::{debug, error, info, warn};
+
+static OPTIONS: RwLock<Option<Arc<YtdlOptions>>> = RwLock::new(None);
+const YTDL_POT_EXTRACTOR_ARG: &str = "getpot_bgutil_baseurl=http://127.0.0.1:{pot_port}";
+const YTDL_COOKIES_ARGS: [&str; 2] = ["--cookies", "{cookies_path}"];
+
+/// yt-dlp command line prepared from the config, again whenever it's reloaded.
+pub struct YtdlOptions {
+    program: &'static str,
+    args: Vec<String>,
+    pot_args: Vec<String>,
+}
+
+impl YtdlOptions {
+    pub fn from_config(config: &Config) -> Result<YtdlOptions, String> {
+        let workarounds = &config.features.music_player.workarounds;
+        let ytdl = &config.features.music_player.ytdl;
+        let vars = template_vars(config);
+        let mut args: Vec<String> = vec![];
+        if let Some(proxy) = &ytdl.proxy {
+            args.push("--proxy".to_string());
+            args.push(proxy.clone());
+        }
+        if !ytdl.format_sort.is_empty() {
+            args.push("-S".to_string());
+            args.push(ytdl.format_sort.join(","));
+        }
+        if workarounds.ytdl_use_cookies {
+            for arg in YTDL_COOKIES_ARGS {
+                args.push(expand(arg, &vars)?);
+            }
+        }
+        let mut extractor_args = vec![];
+        for (extractor, value) in &ytdl.extractor_args {
+            extractor_args.push((extractor.as_str(), expand(value, &vars)?));
+        }
+        let mut extra_args = vec![];
+        for arg in &ytdl.extra_args {
+            extra_args.push(expand(arg, &vars)?);
+        }
+        // Passing `--extractor-args` twice for the same extractor makes the latter win,
+        // so the PO token argument is merged into the user's youtube arguments instead.
+        let pot_arg = expand(YTDL_POT_EXTRACTOR_ARG, &vars)?;
+        let mut pot_extractor_args = extractor_args.clone();
+        match pot_extractor_args
+            .iter_mut()
+            .find(|(name, _)| *name == "youtube")
+        {
+            Some((_, value)) => *value = format!("{};{}", value, pot_arg),
+            None => pot_extractor_args.push(("youtube", pot_arg)),
+        }
+        let build = |extractor_args: Vec<(&str, String)>| {
+            let mut args = args.clone();
+            for (extractor, value) in extractor_args {
+                args.push("--extractor-args".to_string());
+                args.push(format!("{}:{}", extractor, value));
+            }
+            args.extend(extra_args.iter().cloned());
+            args
+        };
+        // Songbird wants a static program name, so it's only leaked again when it changes.
+        let program = match current() {
+            Some(current) if current.program == ytdl.executable => current.program,
+            _ => Box::leak(ytdl.executable.clone().into_boxed_str()),
+        };
+        Ok(YtdlOptions {
+            program,
+            args: build(extractor_args),
+            pot_args: build(pot_extractor_args),
+        })
+    }
+
+    /// Creates a yt-dlp source for `query`, searching YouTube when it isn't a URL.
+    pub fn source(&self, client: HttpClient, query: String, use_pot: bool) -> YoutubeDl {
+        let search = !query.starts_with("http") || query.contains(' ');
+        let src = if search {
+            YoutubeDl::new_search_ytdl_like(self.program, client, query)
+        } else {
+            YoutubeDl::new_ytdl_like(self.program, client, query)
+        };
+        src.user_args(if use_pot {
+            self.pot_args.clone()
+        } else {
+            self.args.clone()
+        })
+    }
+}
+
+/// Placeholders available in the yt-dlp arguments and the PO token server command.
+pub fn template_vars(config: &Config) -> [(&'static str, String); 2] {
+    let workarounds = &config.features.music_player.workarounds;
+    [
+        ("pot_port", workarounds.ytdl_pot_server_port.to_string()),
+        ("cookies_path", workarounds.ytdl_cookies_path.clone()),
+    ]
+}
+
+/// Substitutes `{name}` placeholders, failing on unknown ones so typos surface at startup.
+pub fn expand(template: &str, vars: &[(&str, String)]) -> Result<String, String> {
+    let mut result = template.to_string();
+    for (name, value) in vars {
+        result = result.replace(&format!("{{{}}}", name), value);
+    }
+    let mut rest = result.as_str();
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let name = &rest[start + 1..start + end];
+        if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!(
+                "unknown placeholder '{{{}}}' in '{}'",
+                name, template
+            ));
+        }
+        rest = &rest[start + end..];
+    }
+    Ok(result)
+}
+
+/// Runs `program --version`, returning the version it prints.
+async fn version(program: &str) -> Result<String, String> {
+    match timeout(
+        Duration::from_secs(10),
+        Command::new(program).arg("--version").output(),
+    )
+    .await
+    {
+        Ok(Ok(output)) if output.status.success() => {
+            Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+        }
+        Ok(Ok(output)) => Err(format!(
+            "{} --version exited with {}",
+            program, output.status
+        )),
+        Ok(Err(why)) => Err(format!("failed to run {}: {}", program, why)),
+        Err(_) => Err(format!("timed out while running {} --version", program)),
+    }
+}
+
+/// Checks that yt-dlp can still be run, for the readiness endpoint.
+pub async fn check() -> Result<(), String> {
+    let options = current().ok_or("yt-dlp options are not initialized")?;
+    version(options.program).await.map(|_| ())
+}
+
+/// Prepares the yt-dlp options and checks that the executable can be run.
+pub async fn init(config: &Config) -> Result<(), String> {
+    let options = YtdlOptions::from_config(config)?;
+    debug!("yt-dlp arguments: {:?}", options.args);
+    match version(options.program).await {
+        Ok(version) => info!("Using {} version {}", options.program, version),
+        Err(why) => warn!("{}, tracks may fail to play", why),
+    }
+    *OPTIONS.write().unwrap() = Some(Arc::new(options));
+    Ok(())
+}
+
+/// Prepares the options again from a reloaded config, doing nothing if they were never
+/// initialized.
+pub fn reload(config: &Config) -> Result<(), String> {
+    if current().is_none() {
+        return Ok(());
+    }
+    let options = YtdlOptions::from_config(config)?;
+    debug!("yt-dlp arguments: {:?}", options.args);
+    *OPTIONS.write().unwrap() = Some(Arc::new(options));
+    Ok(())
+}
+
+fn current() -> Option<Arc<YtdlOptions>> {
+    OPTIONS.read().unwrap().clone()
+}
+
+pub fn options() -> Arc<YtdlOptions> {
+    current().expect("yt-dlp options are not initialized")
+}
+
+/// Classified failure of a yt-dlp invocation.
+#[derive(Debug, Clone)]
+pub enum YtdlError {
+    NotFound,
+    AgeRestricted,
+    GeoBlocked,
+    SignInRequired,
+    Network(String),
+    Timeout,
+    Other(String),
+}
+
+impl YtdlError {
+    /// Classifies the error message (which contains yt-dlp's stderr) returned by songbird.
+    pub fn classify(message: &str) -> YtdlError {
+        let lower = message.to_lowercase();
+        let matches = |patterns: &[&str]| patterns.iter().any(|p| lower.contains(p));
+        if matches(&[
+            "confirm your age",
+            "age-restricted",
+            "age restricted",
+            "inappropriate for some users",
+        ]) {
+            YtdlError::AgeRestricted
+        } else if matches(&[
+            "not available in your country",
+            "not made this video available in your country",
+            "blocked it in your country",
+            "geo restriction",
+            "geo-restricted",
+        ]) {
+            YtdlError::GeoBlocked
+        } else if matches(&["not a bot", "sign in", "login required", "use --cookies"]) {
+            YtdlError::SignInRequired
+        } else if matches(&[
+            "no results found",
+            "video unavailable",
+            "this video is unavailable",
+            "unsupported url",
+            "http error 404",
+            "http error 410",
+            "does not exist",
+            "has been removed",
+        ]) {
+            YtdlError::NotFound
+        } else if matches(&["http error 403"]) {
+            // Usually an expired or rejected stream URL, retrying the same one won't help.
+            YtdlError::Other(message.to_string())
+        } else if matches(&[
+            "urlopen error",
+            "timed out",
+            "connection reset",
+            "connection refused",
+            "name resolution",
+            "network is unreachable",
+            "http error 5",
+            "http error 429",
+            "[ssl",
+        ]) {
+            YtdlError::Network(message.to_string())
+        } else {
+            YtdlError::Other(message.to_string())
+        }
+    }
+
+    /// Whether retrying the same invocation could succeed.
+    pub fn is_transient(&self) -> bool {
+        matches!(self, YtdlError::Network(_) | YtdlError::Timeout)
+    }
+
+    /// Message shown to the user who requested the track.
+    pub fn user_message(&self) -> String {
+        match self {
+            YtdlError::NotFound => "Couldn't find anything for that query or URL.",
+            YtdlError::AgeRestricted => "This track is age-restricted and can't be played.",
+            YtdlError::GeoBlocked => "This track isn't available in the region the bot runs in.",
+            YtdlError::SignInRequired => {
+                "YouTube asked the bot to sign in to confirm it's not a bot, please try again later."
+            }
+            YtdlError::Network(_) => {
+                "A network error occurred while fetching the track, please try again."
+            }
+            YtdlError::Timeout => "Timed out while fetching the track, please try again.",
+            YtdlError::Other(_) => "An unknown error occurred while fetching the track.",
+        }
+        .to_string()
+    }
+
+    /// Short name of the error, for metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            YtdlError::NotFound => "not_found",
+            YtdlError::AgeRestricted => "age_restricted",
+            YtdlError::GeoBlocked => "geo_blocked",
+            YtdlError::SignInRequired => "sign_in_required",
+            YtdlError::Network(_) => "network",
+            YtdlError::Timeout => "timeout",
+            YtdlError::Other(_) => "other",
+        }
+    }
+
+    /// Logs the error with the level matching how actionable it is for the bot owner.
+    pub fn log(&self, query: &str) {
+        match self {
+            YtdlError::NotFound | YtdlError::AgeRestricted | YtdlError::GeoBlocked => {
+                debug!("yt-dlp could not play '{}': {:?}", query, self)
+            }
+            YtdlError::SignInRequired => warn!(
+                "yt-dlp hit a sign-in/bot check for '{}', consider enabling cookies or a PO token",
+                query
+            ),
+            YtdlError::Network(why) => warn!("yt-dlp network error for '{}': {}", query, why),
+            YtdlError::Timeout => warn!("yt-dlp timed out for '{}'", query),
+            YtdlError::Other(why) => error!("yt-dlp failed for '{}': {}", query, why),
+        }
+    }
+}
+
+impl fmt::Display for YtdlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            YtdlError::NotFound => write!(f, "not found"),
+            YtdlError::AgeRestricted => write!(f, "age-restricted"),
+            YtdlError::GeoBlocked => write!(f, "geo-blocked"),
+            YtdlError::SignInRequired => write!(f, "sign-in required"),
+            YtdlError::Network(why) => write!(f, "network error: {}", why),
+            YtdlError::Timeout => write!(f, "timed out"),
+            YtdlError::Other(why) => write!(f, "{}", why),
+        }
+    }
+}
+
+impl std::error::Error for YtdlError {}
+
+impl From<AudioStreamError> for YtdlError {
+    fn from(why: AudioStreamError) -> Self {
+        YtdlError::classify(&why.to_string())
+    }
+}
+
+/// Fetches the metadata of `src`, applying the configured timeout and retrying transient errors.
+///
+/// On success the metadata is cached inside `src`, so it won't be queried again.
+pub async fn fetch_metadata(src: &mut YoutubeDl, query: &str) -> Result<AuxMetadata, YtdlError> {
+    let config = &CONFIG.get().unwrap().features.music_player.ytdl;
+    let started = Instant::now();
+    let mut attempt = 0;
+    loop {
+        let result =
+            match timeout(Duration::from_secs(config.timeout_secs), src.aux_metadata()).await {
+                Ok(Ok(metadata)) => {
+                    metrics::ytdl_resolved(started.elapsed(), None);
+                    return Ok(metadata);
+                }
+                Ok(Err(why)) => YtdlError::from(why),
+                Err(_) => YtdlError::Timeout,
+            };
+        if !result.is_transient() || attempt >= config.retries {
+            result.log(query);
+            metrics::ytdl_resolved(started.elapsed(), Some(result.kind()));
+            return Err(result);
+        }
+        let backoff =
+            Duration::from_millis(config.retry_backoff_ms.saturating_mul(1 << attempt.min(16)));
+        debug!(
+            "yt-dlp attempt {} for '{}' failed ({}), retrying in {:?}",
+            attempt + 1,
+            query,
+            result,
+            backoff
+        );
+        sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expands_known_placeholders() {
+        let vars = [
+            ("pot_port", "4416".to_string()),
+            ("cookies_path", "cookies.txt".to_string()),
+        ];
+        assert_eq!(
+            expand("--cookies={cookies_path}", &vars).unwrap(),
+            "--cookies=cookies.txt"
+        );
+        assert_eq!(
+            expand("http://127.0.0.1:{pot_port}/{pot_port}", &vars).unwrap(),
+            "http://127.0.0.1:4416/4416"
+        );
+        assert_eq!(expand("plain", &vars).unwrap(), "plain");
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        let vars = [("pot_port", "4416".to_string())];
+        assert!(expand("--port={port}", &vars).is_err());
+        assert!(expand("{pot_port} {cookie_path}", &vars).is_err());
+    }
+
+    #[test]
+    fn keeps_braces_that_are_not_placeholders() {
+        let vars = [("pot_port", "4416".to_string())];
+        for template in ["{}", "{\"a\": 1}", "{not closed", "{a b}"] {
+            assert_eq!(expand(template, &vars).unwrap(), template);
+        }
+    }
+
+    #[test]
+    fn classifies_ytdl_errors() {
+        let cases = [
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm you’re not a bot. Use --cookies-from-browser or --cookies for the authentication.",
+                "sign_in_required",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm your age. This video may be inappropriate for some users.",
+                "age_restricted",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: The uploader has not made this video available in your country",
+                "geo_blocked",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Video unavailable",
+                "not_found",
+            ),
+            (
+                "ERROR: [youtube:search] ytsearch1:qwertyuiop: No results found",
+                "not_found",
+            ),
+            (
+                "ERROR: unable to download video data: HTTP Error 410: Gone",
+                "not_found",
+            ),
+            (
+                "ERROR: unable to download video data: HTTP Error 403: Forbidden",
+                "other",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Unable to download API page: <urlopen error [Errno -3] Temporary failure in name resolution> (caused by TransportError('<urlopen error [Errno -3] Temporary failure in name resolution>'))",
+                "network",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Unable to download webpage: HTTP Error 429: Too Many Requests",
+                "network",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Unable to download webpage: HTTP Error 503: Service Unavailable",
+                "network",
+            ),
+            (
+                "ERROR: [youtube] dQw4w9WgXcQ: Unable to download webpage: The read operation timed out",
+                "network",
+            ),
+            ("ERROR: Postprocessing: ffprobe not found", "other"),
+        ];
+        for (message, kind) in cases {
+            assert_eq!(YtdlError::classify(message).kind(), kind, "{}", message);
+        }
+    }
+
+    #[test]
+    fn only_network_errors_and_timeouts_are_transient() {
+        let transient = [
+            "ERROR: [youtube] dQw4w9WgXcQ: Unable to download webpage: HTTP Error 429: Too Many Requests",
+            "ERROR: [youtube] dQw4w9WgXcQ: Unable to download API page: <urlopen error [Errno 111] Connection refused>",
+        ];
+        for message in transient {
+            assert!(YtdlError::classify(message).is_transient(), "{}", message);
+        }
+        assert!(YtdlError::Timeout.is_transient());
+        let permanent = [
+            "ERROR: unable to download video data: HTTP Error 403: Forbidden",
+            "ERROR: unable to download video data: HTTP Error 410: Gone",
+            "ERROR: [youtube] dQw4w9WgXcQ: Video unavailable",
+            "ERROR: [youtube] dQw4w9WgXcQ: Sign in to confirm you’re not a bot.",
+        ];
+        for message in permanent {
+            assert!(!YtdlError::classify(message).is_transient(), "{}", message);
+        }
+    }
+}
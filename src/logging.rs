@@ -1,10 +1,188 @@
+use crate::config::{FileLog, Log, LogFormat, LogRotation};
 use chrono::Local;
-use std::{fs::OpenOptions, fs::create_dir, path::Path};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+use tracing_subscriber::layer::Layered;
 use tracing_subscriber::{
-    self, EnvFilter, Registry, fmt, layer::SubscriberExt, util::SubscriberInitExt,
+    self, EnvFilter, Layer, Registry, fmt, layer::SubscriberExt, reload, util::SubscriberInitExt,
 };
 
-pub fn setup(level: &str, file_name: Option<&str>) -> Result<(), ()> {
+type Layers = Vec<Box<dyn Layer<Registry> + Send + Sync>>;
+
+/// Handle to replace the filter while the bot is running.
+static FILTER: OnceLock<reload::Handle<EnvFilter, Layered<Layers, Registry>>> = OnceLock::new();
+
+/// Log file that moves on to a new file every period or once it's too large, deleting the
+/// oldest files past the retention limit.
+struct RollingFile {
+    directory: PathBuf,
+    /// File name with chrono format specifiers.
+    pattern: String,
+    rotation: LogRotation,
+    max_size: u64,
+    max_files: usize,
+    file: File,
+    size: u64,
+    /// Period the current file was opened in.
+    period: String,
+}
+
+impl RollingFile {
+    fn open(config: &FileLog) -> io::Result<RollingFile> {
+        let directory = PathBuf::from(&config.directory);
+        fs::create_dir_all(&directory)?;
+        // Keep appending to an existing file, like when the name has no time in it.
+        let path = directory.join(Local::now().format(&config.path).to_string());
+        let file = OpenOptions::new().append(true).create(true).open(&path)?;
+        let size = file.metadata()?.len();
+        let rolling = RollingFile {
+            directory,
+            pattern: config.path.clone(),
+            rotation: config.rotation,
+            max_size: config.max_size_mb * 1024 * 1024,
+            max_files: config.max_files,
+            file,
+            size,
+            period: period(config.rotation),
+        };
+        rolling.remove_old_files();
+        Ok(rolling)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        let name = Local::now().format(&self.pattern).to_string();
+        let mut path = self.directory.join(&name);
+        // The name can repeat when files are rotated by size or it has no time in it.
+        let mut index = 1;
+        while path.exists() {
+            path = self.directory.join(format!("{}.{}", name, index));
+            index += 1;
+        }
+        self.file = OpenOptions::new().append(true).create(true).open(&path)?;
+        self.size = 0;
+        self.period = period(self.rotation);
+        self.remove_old_files();
+        Ok(())
+    }
+
+    /// Deletes the oldest log files once there are more than `max_files`.
+    fn remove_old_files(&self) {
+        let prefix = file_prefix(&self.pattern);
+        if self.max_files == 0 || prefix.is_empty() {
+            return;
+        }
+        let Ok(entries) = fs::read_dir(&self.directory) else {
+            return;
+        };
+        let mut files: Vec<(PathBuf, SystemTime)> = entries
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                if !metadata.is_file() {
+                    return None;
+                }
+                Some((entry.path(), metadata.modified().ok()?))
+            })
+            .collect();
+        files.sort_by_key(|(_, modified)| std::cmp::Reverse(*modified));
+        for (path, _) in files.into_iter().skip(self.max_files) {
+            // The logger can't log its own errors.
+            if let Err(why) = fs::remove_file(&path) {
+                eprintln!("Failed to delete old log file {}: {}", path.display(), why);
+            }
+        }
+    }
+}
+
+impl Write for RollingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let too_large =
+            self.max_size > 0 && self.size > 0 && self.size + buf.len() as u64 > self.max_size;
+        if too_large || period(self.rotation) != self.period {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Part of the file name pattern before the first format specifier, which all the log files
+/// share.
+pub fn file_prefix(pattern: &str) -> &str {
+    pattern.split('%').next().unwrap_or_default()
+}
+
+/// Identifies the current rotation period, a new file is started when it changes.
+fn period(rotation: LogRotation) -> String {
+    match rotation {
+        LogRotation::Never => String::new(),
+        LogRotation::Hourly => Local::now().format("%Y%m%d%H").to_string(),
+        LogRotation::Daily => Local::now().format("%Y%m%d").to_string(),
+    }
+}
+
+/// Level of the bot's own logs, `LOG_LEVEL` takes precedence over the config.
+pub fn level(config: &Log) -> String {
+    env::var("LOG_LEVEL").unwrap_or_else(|_| config.level.clone())
+}
+
+/// Builds the filter from `RUST_LOG`, the level and the per-target filters of the config.
+fn filter(config: &Log) -> Result<EnvFilter, String> {
+    let mut filter = EnvFilter::builder()
+        .from_env()
+        .map_err(|why| why.to_string())?;
+    let directives = std::iter::once(format!("destiny={}", level(config).to_lowercase())).chain(
+        config
+            .filters
+            .iter()
+            .map(|(target, level)| format!("{}={}", target, level.to_lowercase())),
+    );
+    for directive in directives {
+        let parsed = directive
+            .parse()
+            .map_err(|why| format!("invalid directive '{}': {}", directive, why))?;
+        filter = filter.add_directive(parsed);
+    }
+    Ok(filter)
+}
+
+/// Replaces the active filter with `directives`, in `RUST_LOG` syntax.
+pub fn set_filter(directives: &str) -> Result<(), String> {
+    let filter = EnvFilter::try_new(directives).map_err(|why| why.to_string())?;
+    FILTER
+        .get()
+        .ok_or("logging isn't set up")?
+        .reload(filter)
+        .map_err(|why| why.to_string())
+}
+
+/// Goes back to the filter of the config.
+pub fn reset_filter(config: &Log) -> Result<(), String> {
+    FILTER
+        .get()
+        .ok_or("logging isn't set up")?
+        .reload(filter(config)?)
+        .map_err(|why| why.to_string())
+}
+
+pub fn current_filter() -> String {
+    FILTER
+        .get()
+        .and_then(|handle| handle.with_current(|filter| filter.to_string()).ok())
+        .unwrap_or_default()
+}
+
+pub fn setup(config: &Log) -> io::Result<()> {
     let formatter = fmt::format()
         .with_level(true)
         .with_target(true)
@@ -12,31 +190,147 @@ pub fn setup(level: &str, file_name: Option<&str>) -> Result<(), ()> {
         .with_file(false)
         .with_line_number(true)
         .with_thread_names(false);
-    let filter = EnvFilter::builder()
-        .from_env()
-        .unwrap()
-        .add_directive(format!("destiny={}", level.to_lowercase()).parse().unwrap());
-    // This is dumb af but it works.
-    if file_name.is_some() {
-        if !Path::new("./log").exists() {
-            create_dir("./log").unwrap();
-        }
-        let actual_file_name = Local::now().format(file_name.unwrap()).to_string();
-        let log_file = OpenOptions::new()
-            .append(true)
-            .create(true)
-            .open(format!("./log/{}", actual_file_name))
-            .unwrap();
-        let subscriber = Registry::default()
-            .with(fmt::layer().event_format(formatter).with_ansi(true))
-            .with(fmt::layer().with_ansi(false).with_writer(log_file))
-            .with(filter);
-        subscriber.init();
-    } else {
-        let subscriber = tracing_subscriber::fmt()
-            .event_format(formatter)
-            .with_env_filter(filter);
-        subscriber.init();
+    let (filter, handle) = reload::Layer::new(filter(config).map_err(io::Error::other)?);
+    let mut layers: Layers = vec![match config.format {
+        LogFormat::Text => fmt::layer()
+            .event_format(formatter.clone())
+            .with_ansi(true)
+            .boxed(),
+        LogFormat::Json => fmt::layer().json().boxed(),
+    }];
+    if config.file.enabled {
+        let writer = Mutex::new(RollingFile::open(&config.file)?);
+        layers.push(match config.file.format {
+            LogFormat::Text => fmt::layer()
+                .event_format(formatter)
+                .with_ansi(false)
+                .with_writer(writer)
+                .boxed(),
+            LogFormat::Json => fmt::layer().json().with_writer(writer).boxed(),
+        });
     }
+    Registry::default().with(layers).with(filter).init();
+    let _ = FILTER.set(handle);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use std::time::Duration;
+
+    /// Empty directory of its own in the temp directory.
+    fn temp_directory(name: &str) -> PathBuf {
+        let directory =
+            env::temp_dir().join(format!("destiny-logging-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    fn file_config(directory: &Path, path: &str, max_files: usize) -> FileLog {
+        FileLog {
+            enabled: true,
+            path: path.to_string(),
+            directory: directory.to_str().unwrap().to_string(),
+            max_files,
+            ..FileLog::default()
+        }
+    }
+
+    fn file_names(directory: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn rotates_when_the_file_is_too_large() {
+        let directory = temp_directory("size");
+        let mut rolling = RollingFile::open(&file_config(&directory, "bot.log", 0)).unwrap();
+        rolling.max_size = 16;
+        rolling.write_all(b"first line\n").unwrap();
+        rolling.write_all(b"second line\n").unwrap();
+        // A single write larger than the limit still goes to one file.
+        rolling
+            .write_all(b"a line longer than the limit\n")
+            .unwrap();
+        rolling.flush().unwrap();
+        assert_eq!(
+            file_names(&directory),
+            ["bot.log", "bot.log.1", "bot.log.2"]
+        );
+        assert_eq!(
+            fs::read_to_string(directory.join("bot.log")).unwrap(),
+            "first line\n"
+        );
+        assert_eq!(
+            fs::read_to_string(directory.join("bot.log.2")).unwrap(),
+            "a line longer than the limit\n"
+        );
+    }
+
+    #[test]
+    fn rotates_when_the_period_changes() {
+        let directory = temp_directory("period");
+        let mut rolling = RollingFile::open(&file_config(&directory, "bot.log", 0)).unwrap();
+        rolling.write_all(b"today\n").unwrap();
+        rolling.period = "19700101".to_string();
+        rolling.write_all(b"tomorrow\n").unwrap();
+        assert_eq!(rolling.period, period(LogRotation::Daily));
+        assert_eq!(file_names(&directory), ["bot.log", "bot.log.1"]);
+
+        let mut rolling = RollingFile::open(&FileLog {
+            rotation: LogRotation::Never,
+            ..file_config(&directory, "other.log", 0)
+        })
+        .unwrap();
+        rolling.write_all(b"forever\n").unwrap();
+        assert_eq!(
+            file_names(&directory),
+            ["bot.log", "bot.log.1", "other.log"]
+        );
+    }
+
+    #[test]
+    fn removes_the_oldest_files() {
+        let directory = temp_directory("retention");
+        let now = SystemTime::now();
+        for (age, name) in [
+            (4, "bot-1.log"),
+            (3, "bot-2.log"),
+            (2, "bot-3.log"),
+            (5, "notes.txt"),
+        ] {
+            let file = File::create(directory.join(name)).unwrap();
+            file.set_modified(now - Duration::from_secs(age * 3600))
+                .unwrap();
+        }
+        fs::create_dir(directory.join("bot-dir")).unwrap();
+        RollingFile::open(&file_config(&directory, "bot-%Y%m%d-%H%M%S.log", 3)).unwrap();
+        let names = file_names(&directory);
+        // The new file and the two most recent ones are kept, other files are left alone.
+        assert_eq!(names.len(), 5, "{:?}", names);
+        assert!(!names.contains(&"bot-1.log".to_string()));
+        for kept in ["bot-2.log", "bot-3.log", "bot-dir", "notes.txt"] {
+            assert!(names.contains(&kept.to_string()), "{:?}", names);
+        }
+    }
+
+    #[test]
+    fn keeps_every_file_without_a_prefix_or_limit() {
+        let directory = temp_directory("unlimited");
+        for name in ["a.log", "b.log", "c.log"] {
+            File::create(directory.join(name)).unwrap();
+        }
+        RollingFile::open(&file_config(&directory, "%Y%m%d.log", 1)).unwrap();
+        assert_eq!(file_names(&directory).len(), 4);
+        assert_eq!(file_prefix("%Y%m%d.log"), "");
+        assert_eq!(file_prefix("destiny-%Y.log"), "destiny-");
+        assert_eq!(file_prefix("destiny.log"), "destiny.log");
+    }
+}
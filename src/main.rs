@@ -1,62 +1,102 @@
 use crate::{
+    cli::{Cli, CliCommand},
     commands::{Context, Error},
-    config::Config,
+    config::{Config, SharedConfig},
     utils::message::{info_reply, send_reply},
 };
+use clap::Parser;
 use commands::music::HttpKey;
 use dotenvy::dotenv;
 use reqwest::Client as HttpClient;
-use serenity::all::Ready;
+use serenity::all::{Interaction, Message, Ready};
 use serenity::prelude::*;
 use serenity::{async_trait, gateway::ActivityData};
 use songbird::SerenityInit;
 use std::{env, path::Path, sync::Arc};
-use tokio::sync::OnceCell;
 use tracing::{error, info};
 
+mod cli;
 mod commands;
 mod config;
 mod logging;
+mod metrics;
+mod reload;
+mod server;
+mod storage;
 mod utils;
 
-pub static CONFIG: OnceCell<Config> = OnceCell::const_new();
+pub static CONFIG: SharedConfig = SharedConfig::new();
 
 struct Handler;
 
 #[async_trait]
 impl EventHandler for Handler {
     async fn ready(&self, _: serenity::client::Context, ready: Ready) {
+        server::set_ready();
         info!(
             "Connected to Discord as '{}#{}'",
             ready.user.name,
             ready.user.discriminator.unwrap()
         );
     }
+
+    async fn message(&self, ctx: serenity::client::Context, msg: Message) {
+        if CONFIG.get().unwrap().features.music_player.enabled {
+            commands::music::requests::handle_message(&ctx, &msg).await;
+        }
+    }
+
+    async fn interaction_create(&self, ctx: serenity::client::Context, interaction: Interaction) {
+        if let Interaction::Component(component) = interaction
+            && component
+                .data
+                .custom_id
+                .starts_with(commands::music::player::BUTTON_PREFIX)
+        {
+            commands::music::player::handle_button(&ctx, &component).await;
+        }
+    }
 }
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 32)]
 async fn main() {
     // .env is not required for our code to work.
     let _ = dotenv();
-    let discord_token = env::var("DISCORD_TOKEN").expect("Discord token not found.");
-    let config: Config;
-    if Path::new("./config.toml").exists() {
-        config = config::Config::load("./config.toml");
-    } else {
-        config = config::Config::new();
-        println!("Config file not found. Creating a new one...");
-        config.save("./config.toml");
+    let cli = Cli::parse();
+    match &cli.command {
+        Some(CliCommand::InitConfig { force }) => cli::init_config(&cli, *force),
+        Some(CliCommand::CheckConfig) => cli::check_config(&cli),
+        Some(CliCommand::RegisterCommands { guild }) => cli::register_commands(&cli, *guild).await,
+        Some(CliCommand::UnregisterCommands { guild }) => cli::unregister_commands(*guild).await,
+        Some(CliCommand::Version) => cli::version(),
+        Some(CliCommand::Run) | None => run(cli).await,
     }
-    let level_str = config.log.level.clone();
-    let log_level = env::var("LOG_LEVEL").unwrap_or(level_str);
-    let log_file_name: Option<&str> = match &config.log.file.enabled {
-        true => Some(&config.log.file.path),
-        false => None,
+}
+
+async fn run(cli: Cli) {
+    if !Path::new(&cli.config).exists() {
+        eprintln!("Config file not found. Creating a new one...");
+        Config::default().save(&cli.config);
+    }
+    let overrides = cli.overrides();
+    let config = match Config::load(&cli.config, &overrides) {
+        Ok(config) => config,
+        Err(why) => {
+            // Logging isn't set up yet, it depends on the config.
+            eprintln!("Invalid config: {}", why);
+            std::process::exit(1);
+        }
     };
-    logging::setup(&log_level, log_file_name).expect("Failed to setup logging.");
-    CONFIG
-        .set(config.clone())
-        .expect("Failed to register config to global state.");
+    if cli.print_config {
+        print!("{}", toml::to_string(&config.redacted()).unwrap());
+        return;
+    }
+    let discord_token = env::var("DISCORD_TOKEN").expect("Discord token not found.");
+    let log_level = logging::level(&config.log);
+    logging::setup(&config.log).expect("Failed to setup logging.");
+    CONFIG.set(config.clone());
+    reload::init(cli.config.clone(), overrides);
+    reload::watch();
     info!(
         "Destiny v{} - {}",
         env!("CARGO_PKG_VERSION"),
@@ -65,53 +105,111 @@ async fn main() {
     info!("Log level: {}", log_level);
     info!("Initializing Discord client...");
 
-    // Login with a bot token from the environment
-    let mut commands: Vec<
-        poise::Command<
-            crate::commands::Data,
-            Box<(dyn serde::ser::StdError + std::marker::Send + Sync + 'static)>,
-        >,
-    > = vec![about(), commands::age::age(), commands::ping::ping()];
     if config.features.music_player.enabled {
         info!("Music player enabled.");
-        commands.append(&mut commands::music::exports());
+        if let Err(why) = commands::music::ytdl::init(&config).await {
+            error!("Failed to initialize yt-dlp: {}", why);
+            std::process::exit(1);
+        }
+        if config.features.music_player.workarounds.ytdl_use_cookies {
+            commands::music::cookies::check(&config);
+        }
+        if config.features.music_player.workarounds.ytdl_use_pot
+            && let Err(why) = commands::music::pot::init(&config)
+        {
+            // Left unhealthy, so replies mention that tracks are fetched without it.
+            error!("Failed to start the PO token server: {}", why);
+        }
     }
+    let commands = commands::all(&config);
     let framework = poise::Framework::builder()
         .options(poise::FrameworkOptions {
             commands,
             prefix_options: poise::PrefixFrameworkOptions {
-                prefix: Some(config.general.prefix.into()),
+                prefix: Some(config.general.prefix),
                 edit_tracker: Some(Arc::new(poise::EditTracker::for_timespan(
                     std::time::Duration::from_secs(3600),
                 ))),
                 case_insensitive_commands: true,
                 ..Default::default()
             },
+            post_command: |ctx| {
+                Box::pin(async move {
+                    metrics::command_finished(&ctx.command().qualified_name, "success");
+                })
+            },
+            on_error: |error| {
+                Box::pin(async move {
+                    metrics::command_failed(&error);
+                    if let Err(why) = poise::builtins::on_error(error).await {
+                        error!("Failed to handle a command error: {}", why);
+                    }
+                })
+            },
             ..Default::default()
         })
         .setup(|ctx, _ready, framework| {
             Box::pin(async move {
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                let config = CONFIG.get().unwrap();
+                commands::register(&ctx.http, &framework.options().commands, &config).await?;
                 Ok(commands::Data {})
             })
         })
         .build();
+    let songbird = songbird::Songbird::serenity();
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
     let mut client = Client::builder(discord_token, intents)
         .event_handler(Handler)
         .framework(framework)
-        .register_songbird()
+        .register_songbird_with(songbird.clone())
         .type_map_insert::<HttpKey>(HttpClient::new())
         .activity(ActivityData::playing("music!"))
         .await
         .expect("Error creating client");
 
+    if config.http.enabled() {
+        if config.http.metrics {
+            metrics::init();
+        }
+        let state = server::State {
+            config: config.http.clone(),
+            shard_manager: client.shard_manager.clone(),
+            songbird,
+        };
+        if let Err(why) = server::start(state).await {
+            error!("Failed to start the HTTP server: {}", why);
+        }
+    }
+
+    let shard_manager = client.shard_manager.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down...");
+        commands::music::pot::shutdown();
+        shard_manager.shutdown_all().await;
+    });
+
     info!("Starting client...");
     if let Err(why) = client.start_autosharded().await {
         error!("An error occurred while running the client: {:?}", why);
     }
 }
 
+/// Waits for Ctrl+C, or SIGTERM on Unix.
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+        let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM.");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
 /// Shows the latency between the bot and Discord server.
 #[poise::command(slash_command, prefix_command)]
 pub async fn about(ctx: Context<'_>) -> Result<(), Error> {
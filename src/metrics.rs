@@ -0,0 +1,133 @@
+use crate::commands::{Data, Error};
+use poise::FrameworkError;
+use prometheus::{
+    GaugeVec, Histogram, IntCounter, IntCounterVec, IntGauge, TextEncoder, register_gauge_vec,
+    register_histogram, register_int_counter, register_int_counter_vec, register_int_gauge,
+};
+use serenity::all::ShardManager;
+use songbird::Songbird;
+use std::sync::LazyLock;
+use std::time::Duration;
+use tracing::error;
+
+static COMMANDS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "destiny_commands_total",
+        "Commands run, by name and outcome",
+        &["command", "outcome"]
+    )
+    .unwrap()
+});
+static VOICE_CONNECTIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "destiny_voice_connections",
+        "Voice channels the bot is connected to"
+    )
+    .unwrap()
+});
+static QUEUED_TRACKS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!(
+        "destiny_queued_tracks",
+        "Tracks in every queue, including the ones playing"
+    )
+    .unwrap()
+});
+static YTDL_RESOLVE_SECONDS: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "destiny_ytdl_resolve_seconds",
+        "Time taken by yt-dlp to resolve a track, retries included",
+        vec![0.5, 1.0, 2.0, 3.0, 5.0, 8.0, 13.0, 21.0, 34.0]
+    )
+    .unwrap()
+});
+static YTDL_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "destiny_ytdl_failures_total",
+        "Tracks yt-dlp failed to resolve, by reason",
+        &["reason"]
+    )
+    .unwrap()
+});
+static GATEWAY_LATENCY: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "destiny_gateway_latency_seconds",
+        "Heartbeat latency of each gateway shard",
+        &["shard"]
+    )
+    .unwrap()
+});
+static TRACK_ERRORS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "destiny_track_errors_total",
+        "Tracks that failed while playing"
+    )
+    .unwrap()
+});
+
+/// Registers every metric, so they're exported before anything is recorded.
+pub fn init() {
+    LazyLock::force(&COMMANDS);
+    LazyLock::force(&VOICE_CONNECTIONS);
+    LazyLock::force(&QUEUED_TRACKS);
+    LazyLock::force(&YTDL_RESOLVE_SECONDS);
+    LazyLock::force(&YTDL_FAILURES);
+    LazyLock::force(&GATEWAY_LATENCY);
+    LazyLock::force(&TRACK_ERRORS);
+}
+
+pub fn command_finished(command: &str, outcome: &str) {
+    COMMANDS.with_label_values(&[command, outcome]).inc();
+}
+
+/// Counts a command that failed or was refused before running.
+pub fn command_failed(error: &FrameworkError<'_, Data, Error>) {
+    let Some(ctx) = error.ctx() else {
+        return;
+    };
+    let outcome = match error {
+        FrameworkError::Command { .. } | FrameworkError::CommandPanic { .. } => "error",
+        FrameworkError::ArgumentParse { .. } => "invalid_arguments",
+        _ => "rejected",
+    };
+    command_finished(&ctx.command().qualified_name, outcome);
+}
+
+/// Records a yt-dlp resolution, `failure` being the reason it failed.
+pub fn ytdl_resolved(duration: Duration, failure: Option<&str>) {
+    YTDL_RESOLVE_SECONDS.observe(duration.as_secs_f64());
+    if let Some(reason) = failure {
+        YTDL_FAILURES.with_label_values(&[reason]).inc();
+    }
+}
+
+pub fn track_failed() {
+    TRACK_ERRORS.inc();
+}
+
+/// Updates the gauges read from the client and encodes every metric.
+pub async fn render(shard_manager: &ShardManager, songbird: &Songbird) -> String {
+    GATEWAY_LATENCY.reset();
+    for (id, runner) in shard_manager.runners.lock().await.iter() {
+        if let Some(latency) = runner.latency {
+            GATEWAY_LATENCY
+                .with_label_values(&[&id.to_string()])
+                .set(latency.as_secs_f64());
+        }
+    }
+    let (mut connections, mut tracks) = (0, 0);
+    for (_, call) in songbird.iter() {
+        let call = call.lock().await;
+        if call.current_channel().is_some() {
+            connections += 1;
+        }
+        tracks += call.queue().len() as i64;
+    }
+    VOICE_CONNECTIONS.set(connections);
+    QUEUED_TRACKS.set(tracks);
+    TextEncoder::new()
+        .encode_to_string(&prometheus::gather())
+        .unwrap_or_else(|why| {
+            error!("Failed to encode the metrics: {}", why);
+            String::new()
+        })
+}
@@ -0,0 +1,188 @@
+use crate::CONFIG;
+use crate::commands::music::ytdl;
+use crate::config::{Config, ConfigError, Override};
+use crate::logging;
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+/// How often the config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Keys only read at startup, a new value takes effect after a restart.
+const RESTART_REQUIRED: [&str; 9] = [
+    "log.format",
+    "log.file",
+    "general.prefix",
+    "storage",
+    "http",
+    "features.music_player.enabled",
+    // The PO token server and its health checks keep using the port they started with.
+    "features.music_player.workarounds.ytdl_pot_server_port",
+    "features.music_player.pot_server",
+    "features.music_player.lyrics.lrclib",
+];
+
+/// Path and overrides the config was loaded with, to load it the same way again.
+static SOURCE: OnceLock<(String, Vec<Override>)> = OnceLock::new();
+
+/// Keys whose value changed with a reload.
+pub struct Changes {
+    pub applied: Vec<String>,
+    pub restart_required: Vec<String>,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.restart_required.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "Nothing changed.".to_string();
+        }
+        let mut summary = String::new();
+        if !self.applied.is_empty() {
+            summary.push_str("Applied:");
+            for key in &self.applied {
+                summary.push_str(&format!("\n- `{}`", key));
+            }
+        }
+        if !self.restart_required.is_empty() {
+            if !summary.is_empty() {
+                summary.push_str("\n\n");
+            }
+            summary.push_str("Changed but needs a restart:");
+            for key in &self.restart_required {
+                summary.push_str(&format!("\n- `{}`", key));
+            }
+        }
+        summary
+    }
+}
+
+pub fn init(path: String, overrides: Vec<Override>) {
+    let _ = SOURCE.set((path, overrides));
+}
+
+/// Flattens the config into its dotted keys, arrays are single values.
+fn flatten(value: &toml::Value, prefix: &str, keys: &mut BTreeMap<String, String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(value, &key, keys);
+            }
+        }
+        value => {
+            keys.insert(prefix.to_string(), value.to_string());
+        }
+    }
+}
+
+fn diff(previous: &Config, current: &Config) -> Changes {
+    let mut previous_keys = BTreeMap::new();
+    let mut current_keys = BTreeMap::new();
+    if let (Ok(previous), Ok(current)) = (
+        toml::Value::try_from(previous),
+        toml::Value::try_from(current),
+    ) {
+        flatten(&previous, "", &mut previous_keys);
+        flatten(&current, "", &mut current_keys);
+    }
+    let mut changes = Changes {
+        applied: vec![],
+        restart_required: vec![],
+    };
+    let mut keys: Vec<&String> = previous_keys.keys().chain(current_keys.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    for key in keys {
+        if key == "config_version" || previous_keys.get(key) == current_keys.get(key) {
+            continue;
+        }
+        let restart_required = RESTART_REQUIRED
+            .iter()
+            .any(|prefix| key == prefix || key.starts_with(&format!("{}.", prefix)));
+        if restart_required {
+            changes.restart_required.push(key.clone());
+        } else {
+            changes.applied.push(key.clone());
+        }
+    }
+    changes
+}
+
+/// Loads the config file again and swaps it in if it's valid, keeping the current one otherwise.
+/// The file is never migrated here, writing it would trigger another reload.
+pub fn reload() -> Result<Changes, ConfigError> {
+    let (path, overrides) = SOURCE.get().unwrap();
+    let (config, unknown_keys) = Config::check(path, overrides)?;
+    for key in &unknown_keys {
+        warn!("Unknown key `{}` in {}, it's ignored", key, path);
+    }
+    // The config is always loaded at startup, before anything can reload it.
+    let previous = CONFIG.set(config.clone()).unwrap();
+    let changes = diff(&previous, &config);
+    if changes.applied.iter().any(|key| key.starts_with("log."))
+        && let Err(why) = logging::reset_filter(&config.log)
+    {
+        error!("Failed to apply the new log filters: {}", why);
+    }
+    // The cookies and the PO token port are part of the yt-dlp arguments too.
+    if changes.applied.iter().any(|key| {
+        key.starts_with("features.music_player.ytdl.")
+            || key.starts_with("features.music_player.workarounds.")
+    }) && let Err(why) = ytdl::reload(&config)
+    {
+        error!("Failed to apply the new yt-dlp options: {}", why);
+    }
+    for key in &changes.applied {
+        info!("Config key {} changed", key);
+    }
+    for key in &changes.restart_required {
+        warn!("Config key {} changed, restart the bot to apply it", key);
+    }
+    Ok(changes)
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Reloads the config whenever its file changes.
+pub fn watch() {
+    tokio::spawn(async move {
+        let path = &SOURCE.get().unwrap().0;
+        let mut last_modified = modified(path);
+        loop {
+            sleep(WATCH_INTERVAL).await;
+            let modified = modified(path);
+            if modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+            if modified.is_none() {
+                warn!(
+                    "The config file {} is gone, keeping the current config",
+                    path
+                );
+                continue;
+            }
+            match reload() {
+                Ok(changes) if !changes.is_empty() => info!("Reloaded the config"),
+                Ok(_) => {}
+                Err(why) => error!("Not reloading the config: {}", why),
+            }
+        }
+    });
+}
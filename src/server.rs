@@ -0,0 +1,201 @@
+use crate::CONFIG;
+use crate::commands::music::{pot, ytdl};
+use crate::config::Http as HttpConfig;
+use crate::metrics;
+use serenity::all::{ConnectionStage, ShardManager};
+use songbird::Songbird;
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{sleep, timeout};
+use tracing::{debug, info};
+
+/// Longest request accepted, only the request line is used.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// How often yt-dlp is run in the background for the readiness endpoint, which only reports
+/// the last result so probes stay cheap.
+const YTDL_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Set once Discord sent the ready event, the shards' status is checked afterwards.
+static READY: AtomicBool = AtomicBool::new(false);
+/// Result of the last yt-dlp check, `None` until the first one finishes.
+static YTDL_STATUS: RwLock<Option<Result<(), String>>> = RwLock::new(None);
+
+/// What the endpoints report on.
+#[derive(Clone)]
+pub struct State {
+    pub config: HttpConfig,
+    pub shard_manager: Arc<ShardManager>,
+    pub songbird: Arc<Songbird>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn text(status: &'static str, body: impl Into<String>) -> Response {
+        Response {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+}
+
+/// Called from the ready event handler.
+pub fn set_ready() {
+    READY.store(true, Ordering::Relaxed);
+}
+
+/// Whether every shard is connected to the gateway.
+async fn check_gateway(shard_manager: &ShardManager) -> Result<String, String> {
+    if !READY.load(Ordering::Relaxed) {
+        return Err("waiting for the ready event".to_string());
+    }
+    let runners = shard_manager.runners.lock().await;
+    let connected = runners
+        .values()
+        .filter(|runner| runner.stage == ConnectionStage::Connected)
+        .count();
+    let status = format!("{}/{} shards connected", connected, runners.len());
+    if connected > 0 && connected == runners.len() {
+        Ok(status)
+    } else {
+        Err(status)
+    }
+}
+
+/// Checks yt-dlp in the background for as long as the bot runs.
+fn watch_ytdl() {
+    tokio::spawn(async {
+        loop {
+            let status = ytdl::check().await;
+            *YTDL_STATUS.write().unwrap() = Some(status);
+            sleep(YTDL_CHECK_INTERVAL).await;
+        }
+    });
+}
+
+/// Reports every readiness check, the slow ones from their last background run.
+async fn readiness(state: &State) -> Response {
+    let mut checks = vec![("gateway", check_gateway(&state.shard_manager).await)];
+    let config = CONFIG.get().unwrap();
+    let music_player = &config.features.music_player;
+    if music_player.enabled {
+        let ytdl = match YTDL_STATUS.read().unwrap().clone() {
+            Some(status) => status.map(|_| "ok".to_string()),
+            None => Err("not checked yet".to_string()),
+        };
+        checks.push(("ytdl", ytdl));
+        // Kept up to date by the PO token server's own health checks.
+        if music_player.workarounds.ytdl_use_pot {
+            let result = if pot::is_healthy() {
+                Ok("ok".to_string())
+            } else {
+                Err("unreachable".to_string())
+            };
+            checks.push(("pot_server", result));
+        }
+    }
+    let ready = checks.iter().all(|(_, result)| result.is_ok());
+    let body: String = checks
+        .into_iter()
+        .map(|(name, result)| match result {
+            Ok(status) => format!("{}: {}\n", name, status),
+            Err(why) => format!("{}: failing, {}\n", name, why),
+        })
+        .collect();
+    if ready {
+        Response::text("200 OK", body)
+    } else {
+        Response::text("503 Service Unavailable", body)
+    }
+}
+
+async fn route(state: &State, method: &str, path: &str) -> Response {
+    if method != "GET" {
+        return Response::text("405 Method Not Allowed", "Method not allowed\n");
+    }
+    // Query strings aren't used by any endpoint.
+    match path.split('?').next().unwrap_or_default() {
+        "/metrics" if state.config.metrics => Response {
+            status: "200 OK",
+            content_type: "text/plain; version=0.0.4; charset=utf-8",
+            body: metrics::render(&state.shard_manager, &state.songbird).await,
+        },
+        "/healthz" if state.config.health => Response::text("200 OK", "ok\n"),
+        "/readyz" if state.config.health => readiness(state).await,
+        _ => Response::text("404 Not Found", "Not found\n"),
+    }
+}
+
+async fn handle(state: &State, mut stream: TcpStream) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = timeout(REQUEST_TIMEOUT, stream.read(&mut buffer))
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+        if request.len() > MAX_REQUEST_SIZE {
+            break;
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .split_whitespace();
+    let (method, path) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    );
+    let response = route(state, method, path).await;
+    let head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        response.content_type,
+        response.body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Serves the enabled endpoints in the background.
+pub async fn start(state: State) -> io::Result<()> {
+    let listener = TcpListener::bind((state.config.address.as_str(), state.config.port)).await?;
+    info!("Serving HTTP endpoints on {}", listener.local_addr()?);
+    if state.config.health && CONFIG.get().unwrap().features.music_player.enabled {
+        watch_ytdl();
+    }
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(why) => {
+                    debug!("Failed to accept an HTTP connection: {:?}", why);
+                    continue;
+                }
+            };
+            let state = state.clone();
+            tokio::spawn(async move {
+                if let Err(why) = handle(&state, stream).await {
+                    debug!("Failed to answer an HTTP request: {:?}", why);
+                }
+            });
+        }
+    });
+    Ok(())
+}
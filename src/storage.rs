@@ -0,0 +1,74 @@
+use crate::CONFIG;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::error;
+
+fn path(name: &str) -> PathBuf {
+    PathBuf::from(&CONFIG.get().unwrap().storage.path).join(format!("{}.json", name))
+}
+
+/// Loads the JSON document `name` from the data directory, or the default when it doesn't exist.
+pub fn load<T: DeserializeOwned + Default>(name: &str) -> T {
+    let path = path(name);
+    match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|why| {
+            error!("Failed to parse {}, starting over: {}", path.display(), why);
+            T::default()
+        }),
+        Err(why) if why.kind() == io::ErrorKind::NotFound => T::default(),
+        Err(why) => {
+            error!("Failed to read {}: {}", path.display(), why);
+            T::default()
+        }
+    }
+}
+
+/// Saves `value` as the JSON document `name`, replacing the previous one atomically.
+pub fn save<T: Serialize>(name: &str, value: &T) -> io::Result<()> {
+    let path = path(name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let temp_path = path.with_extension("json.tmp");
+    fs::write(&temp_path, serde_json::to_vec(value)?)?;
+    fs::rename(temp_path, path)
+}
+
+/// Saves snapshots of a JSON document on a blocking thread, so callers don't wait on the disk.
+///
+/// Snapshots must be passed to [`BackgroundSaver::save`] in the order they were taken, a snapshot
+/// is dropped when a newer one has already been written.
+pub struct BackgroundSaver {
+    name: &'static str,
+    taken: AtomicU64,
+    saved: Mutex<u64>,
+}
+
+impl BackgroundSaver {
+    pub const fn new(name: &'static str) -> BackgroundSaver {
+        BackgroundSaver {
+            name,
+            taken: AtomicU64::new(0),
+            saved: Mutex::new(0),
+        }
+    }
+
+    pub fn save<T: Serialize + Send + 'static>(&'static self, value: T) {
+        let generation = self.taken.fetch_add(1, Ordering::SeqCst) + 1;
+        tokio::task::spawn_blocking(move || {
+            let mut saved = self.saved.lock().unwrap();
+            if *saved > generation {
+                return;
+            }
+            if let Err(why) = save(self.name, &value) {
+                error!("Failed to save {}: {:?}", self.name, why);
+            }
+            *saved = generation;
+        });
+    }
+}
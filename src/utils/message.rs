@@ -15,14 +15,14 @@ pub async fn create_embed(
         .title(title.unwrap_or("Destiny".to_string()))
         .description(description.unwrap_or("".to_string()))
         .color(color);
-    if client.is_some() {
-        let user = client.unwrap().http.get_current_user().await.unwrap();
+    if let Some(client) = client {
+        let user = client.http.get_current_user().await.unwrap();
         embed = embed.footer(
             CreateEmbedFooter::new(user.name.clone())
                 .icon_url(user.avatar_url().unwrap_or("".to_string())),
         )
     }
-    return embed;
+    embed
 }
 
 pub async fn error_embed(
@@ -0,0 +1,11 @@
+use std::time::Duration;
+
+/// Formats a duration as `m:ss`, or `h:mm:ss` when it's at least an hour long.
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}